@@ -18,6 +18,23 @@ waihona={version = "{{version}}", features = ["aws"], default-features = false }
 
 {{readme}}
 
+## Upgrading
+
+### GCP clients no longer expose a cloud-storage `Client`
+
+GCS requests are now made over HTTP by waihona itself, so the public
+`client: cloud_storage::Client` fields of `GcpBuckets` and `GcpBucket`
+are gone, and the cloud-storage `Client` type is no longer part of
+waihona's API. Configure GCP access through `GcpBuckets` instead:
+
+- `GcpBuckets::new` reads credentials from the environment.
+- `GcpBuckets::with_service_account_json` takes the content of a key file.
+- `GcpBuckets::emulator` and `with_endpoint` point requests elsewhere.
+- `with_retry_policy` and `with_timeout_config` control retries and timeouts.
+
+Code that needs the cloud-storage API directly should create its own
+`cloud_storage::Client`.
+
 ## License
 
 This project is opened under the [MIT License](./LICENSE) which allows very broad use for both academic and commercial purposes
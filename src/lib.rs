@@ -9,6 +9,8 @@
 //!  - [x] `aws`: Enable aws provider and dependencies
 //!  - [x] `gcp`: Enable gcp provider and dependencies
 //!  - [x] `azure`: Enable azure provider and dependencies
+//!  - [x] `local`: Enable local filesystem provider, handy for tests and on-prem deployments
+//!  - [x] `memory`: Enable in-memory provider for unit tests and ephemeral storage
 //!
 //!  ## Traits
 //!
@@ -19,6 +21,8 @@
 //!```no_run
 //! // all methods of traits are async
 //!  use bytes::Bytes;
+//!  use waihona::types::blob::{BlobStream, ByteRange};
+//!  use waihona::types::bucket::ListOptions;
 //!
 //!  trait Buckets<T, P>     
 //!      where T: Bucket<P>, P: Blob{    
@@ -32,17 +36,24 @@
 //! trait Bucket<P>
 //!     where P: Blob{
 //!         fn list_blobs(&self, marker: Option<String>);
-//!         fn get_blob(&self, blob_path: &str, content_range: Option<String>);
+//!         fn list_blobs_with_options(&self, options: ListOptions);
+//!         fn list_all_blobs(&self);
+//!         fn get_blob(&self, blob_path: &str, range: Option<ByteRange>);
+//!         fn stat_blob(&self, blob_path: &str);
 //!         fn copy_blob(&self, blob_path: &str, blob_destination_path: &str, content_type: Option<String>);
 //!         fn write_blob(&self, blob_name: &str, content: Option<Bytes>);
+//!         fn write_blob_stream(&self, blob_name: &str, content: BlobStream);
 //!         fn delete_blob(&self, blob_path: &str);
 //!     }
 //!
 //!  trait Blob {
+//!      fn properties(&self);
+//!      fn content_range(&self);
 //!      fn delete(&self);
 //!      fn copy(&self, blob_destination_path: &str, content_type: Option<String> );
 //!      fn write(&self, content: Option<Bytes>);
 //!      fn read(&mut self);
+//!      fn read_stream(&mut self);
 //!     }
 //!
 //!```
@@ -69,10 +80,10 @@
 //!    use waihona::providers::gcp;
 //!    let mut gcp_buckets = providers::gcp::GcpBuckets::new(
 //!        "waihona"
-//!        );
+//!        ).unwrap();
 //!    // Returns (Vec<GcpBucket, Option<String>)
 //!    // where Option<String> is the cursor for the token for next page listing
-//!    let resp = gcp_buckets.list().await;
+//!    let resp = gcp_buckets.list().await.unwrap();
 //!    resp[0]
 //!}
 //!```
@@ -89,15 +100,15 @@
 //!    use waihona::providers;
 //!    let mut aws_buckets = providers::aws::AwsBuckets::new(
 //!        "us-east-2"
-//!        );
+//!        ).unwrap();
 //!    let resp = aws_buckets.exists(
 //!        "waihona"
-//!        ).await;
+//!        ).await.unwrap();
 //!        // OR you can do
 //!    let resp = providers::aws::AwsBucket::exists(
 //!        "us-east-2",
 //!        "waihona"
-//!        ).await;
+//!        ).await.unwrap();
 //!    resp
 //!}
 //!```
@@ -117,7 +128,7 @@
 //!    use waihona::types::blob::{Blob};
 //!    use waihona::providers;
 //!    use bytes::Bytes;
-//!    let mut azure_buckets = providers::azure::AzureBuckets::new("waihona".to_owned());
+//!    let mut azure_buckets = providers::azure::AzureBuckets::new("waihona".to_owned()).unwrap();
 //!    let waihona = azure_buckets.open(
 //!        "waihona",
 //!        ).await.unwrap();
@@ -167,8 +178,373 @@
 //!     gcp_blob
 //!  }
 //!```
+//!
+//! Pick a provider at runtime, e.g from configuration
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_any_provider() {
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers::any::{AnyBuckets, Provider};
+//!    let provider: Provider = "aws".parse().unwrap();
+//!    let mut buckets = AnyBuckets::new(provider, "us-east-2").unwrap();
+//!    let waihona = buckets.open("waihona").await.unwrap();
+//!    let (blobs, _marker) = waihona.list_blobs(None).await.unwrap();
+//!}
+//!```
+//!
+//! Open a bucket from a storage url, blob paths are then relative to the url's prefix
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_open_url() {
+//!    use waihona::types::bucket::Bucket;
+//!    use bytes::Bytes;
+//!    let photos = waihona::open_url("s3://waihona/photos?region=us-east-2")
+//!        .await
+//!        .unwrap();
+//!    // writes photos/hello.txt
+//!    photos.write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+//!        .await
+//!        .unwrap();
+//!}
+//!```
+//!
+//! Stream a file into a bucket without holding it in memory
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "gcp")]
+//!async fn test_write_blob_stream() {
+//!    use waihona::types::blob::stream_from_reader;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut gcp_buckets = providers::gcp::GcpBuckets::new(
+//!        "gcp-project-name"
+//!        ).unwrap();
+//!    let waihona = gcp_buckets.open("waihona").await.unwrap();
+//!    let file = tokio::fs::File::open("backup.tar").await.unwrap();
+//!    waihona.write_blob_stream(
+//!        "backups/backup.tar",
+//!        stream_from_reader(file),
+//!        Some("application/x-tar".to_owned())
+//!        ).await
+//!        .unwrap();
+//!}
+//!```
+//!
+//! Look up a blob's properties without downloading its content
+//!
+//!```rust
+//!#[tokio::test]
+//!#[cfg(feature = "azure")]
+//!async fn test_blob_properties() {
+//!    use waihona::types::blob::Blob;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut azure_buckets = providers::azure::AzureBuckets::new("waihona".to_owned()).unwrap();
+//!    let waihona = azure_buckets.open("waihona").await.unwrap();
+//!    let blob = waihona.stat_blob("example.txt").await.unwrap();
+//!    let properties = blob.properties();
+//!    println!("{} {:?} {:?}", properties.key, properties.size, properties.last_modified);
+//!}
+//!```
+//!
+//! List the "directories" and blobs directly under photos/ on AWS
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_list_directory() {
+//!    use waihona::types::bucket::{Buckets, Bucket, ListOptions};
+//!    use waihona::providers;
+//!    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
+//!    let waihona = aws_buckets.open("waihona").await.unwrap();
+//!    let options = ListOptions {
+//!        prefix: Some("photos/".to_owned()),
+//!        delimiter: Some("/".to_owned()),
+//!        ..Default::default()
+//!    };
+//!    let list = waihona.list_blobs_with_options(options).await.unwrap();
+//!    // e.g ["photos/2020/", "photos/2021/"]
+//!    println!("{:?}", list.prefixes);
+//!}
+//!```
+//!
+//! Walk every blob of a bucket on GCP, pages are fetched as the stream is polled
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "gcp")]
+//!async fn test_list_all_blobs() {
+//!    use futures::TryStreamExt;
+//!    use waihona::types::blob::Blob;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut gcp_buckets = providers::gcp::GcpBuckets::new(
+//!        "gcp-project-name"
+//!        ).unwrap();
+//!    let waihona = gcp_buckets.open("waihona").await.unwrap();
+//!    let mut blobs = waihona.list_all_blobs();
+//!    while let Some(blob) = blobs.try_next().await.unwrap() {
+//!        println!("{}", blob.properties().key);
+//!    }
+//!}
+//!```
+//!
+//! Tell a missing blob apart from a forbidden one, whatever the provider
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_error_kind() {
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::types::errors::ErrorKind;
+//!    use waihona::providers;
+//!    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
+//!    let waihona = aws_buckets.open("waihona").await.unwrap();
+//!    match waihona.get_blob("example.txt", None).await {
+//!        Ok(_) => {}
+//!        Err(e) if e.kind() == ErrorKind::NotFound => println!("missing"),
+//!        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+//!            println!("forbidden, request id {:?}", e.request_id())
+//!        }
+//!        Err(e) => println!("{} (status {:?})", e, e.status()),
+//!    }
+//!}
+//!```
+//!
+//! Retry throttled and dropped requests with exponential backoff
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "azure")]
+//!async fn test_retry_policy() {
+//!    use std::time::Duration;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::types::retry::RetryPolicy;
+//!    use waihona::providers;
+//!    let mut azure_buckets = providers::azure::AzureBuckets::new("waihona".to_owned())
+//!        .unwrap()
+//!        .with_retry_policy(RetryPolicy {
+//!            max_attempts: 5,
+//!            base_delay: Duration::from_millis(100),
+//!            max_delay: Duration::from_secs(5),
+//!            ..Default::default()
+//!        });
+//!    // containers opened from here and their blobs use the same policy
+//!    let waihona = azure_buckets.open("waihona").await.unwrap();
+//!    let blob = waihona.get_blob("example.txt", None).await.unwrap();
+//!}
+//!```
+//!
+//! Bound how long requests may take, a single call can be given its own within a scope
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_timeout_config() {
+//!    use std::time::Duration;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::types::errors::ErrorKind;
+//!    use waihona::types::timeout::TimeoutConfig;
+//!    use waihona::providers;
+//!    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2")
+//!        .unwrap()
+//!        .with_timeout_config(TimeoutConfig {
+//!            connect: Some(Duration::from_secs(5)),
+//!            request: Some(Duration::from_secs(30)),
+//!            operation: Some(Duration::from_secs(120)),
+//!        });
+//!    let waihona = aws_buckets.open("waihona").await.unwrap();
+//!    // fail fast on a latency sensitive read
+//!    let fast = TimeoutConfig {
+//!        request: Some(Duration::from_millis(500)),
+//!        operation: Some(Duration::from_secs(2)),
+//!        ..Default::default()
+//!    };
+//!    match fast.scope(waihona.get_blob("example.txt", None)).await {
+//!        Err(e) if e.kind() == ErrorKind::Timeout => println!("too slow"),
+//!        resp => { resp.unwrap(); }
+//!    }
+//!}
+//!```
+//!
+//! Give every tenant its own credentials instead of reading them from the environment
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(all(feature = "aws", feature = "gcp", feature = "azure"))]
+//!async fn test_tenant_credentials() {
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut aws_buckets = providers::aws::AwsBuckets::builder()
+//!        .region("eu-west-2")
+//!        .credentials("AKIDEXAMPLE", "tenant-secret")
+//!        .build()
+//!        .unwrap();
+//!    let service_account = std::fs::read_to_string("tenant.json").unwrap();
+//!    let mut gcp_buckets = providers::gcp::GcpBuckets::with_service_account_json(
+//!        "tenant-project",
+//!        &service_account,
+//!    )
+//!    .unwrap();
+//!    let mut azure_buckets = providers::azure::AzureBuckets::with_sas_token(
+//!        "tenantaccount",
+//!        "?sv=2020-08-04&ss=b&srt=sco&sp=rl&sig=signature",
+//!    )
+//!    .unwrap();
+//!    let aws = aws_buckets.open("tenant-bucket").await.unwrap();
+//!    let gcp = gcp_buckets.open("tenant-bucket").await.unwrap();
+//!    let azure = azure_buckets.open("tenant-container").await.unwrap();
+//!    println!("{}", aws.list_blobs(None).await.unwrap().0.len());
+//!    println!("{}", gcp.list_blobs(None).await.unwrap().0.len());
+//!    println!("{}", azure.list_blobs(None).await.unwrap().0.len());
+//!}
+//!```
+//!
+//! Point clients at S3 compatible stores and local emulators such as MinIO, Azurite and fake-gcs-server
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(all(feature = "aws", feature = "gcp", feature = "azure"))]
+//!async fn test_local_emulators() {
+//!    use bytes::Bytes;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut minio = providers::aws::AwsBuckets::builder()
+//!        .endpoint("http://localhost:9000")
+//!        .credentials("minioadmin", "minioadmin")
+//!        .build()
+//!        .unwrap();
+//!    let mut azurite =
+//!        providers::azure::AzureBuckets::emulator("http://127.0.0.1:10000")
+//!            .unwrap();
+//!    let mut fake_gcs =
+//!        providers::gcp::GcpBuckets::emulator("test", "http://localhost:4443");
+//!    minio.create("waihona", None).await.unwrap();
+//!    azurite.create("waihona", None).await.unwrap();
+//!    fake_gcs.create("waihona", None).await.unwrap();
+//!    let bucket = minio.open("waihona").await.unwrap();
+//!    bucket
+//!        .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+//!        .await
+//!        .unwrap();
+//!}
+//!```
+//!
+//! Hand out signed urls so browsers read and upload blobs without credentials
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_signed_urls() {
+//!    use waihona::types::blob::HttpMethod;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    use std::time::Duration;
+//!    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
+//!    let bucket = aws_buckets.open("waihona").await.unwrap();
+//!    let expires_in = Duration::from_secs(900);
+//!    let upload = bucket
+//!        .signed_url("uploads/photo.png", HttpMethod::Put, expires_in)
+//!        .await
+//!        .unwrap();
+//!    let download = bucket
+//!        .signed_url("uploads/photo.png", HttpMethod::Get, expires_in)
+//!        .await
+//!        .unwrap();
+//!    println!("PUT {} then GET {}", upload, download);
+//!}
+//!```
+//!
+//! Let browsers upload images of up to 10MB from an HTML form
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "gcp")]
+//!async fn test_post_policy() {
+//!    use waihona::types::bucket::{Buckets, Bucket, PostConditions};
+//!    use waihona::providers;
+//!    use std::time::Duration;
+//!    let mut gcp_buckets = providers::gcp::GcpBuckets::new(
+//!        "gcp-project-name"
+//!        ).unwrap();
+//!    let bucket = gcp_buckets.open("waihona").await.unwrap();
+//!    let conditions = PostConditions {
+//!        key_prefix: "uploads/".to_owned(),
+//!        content_length_range: Some(1..=10 << 20),
+//!        content_type: Some("image/png".to_owned()),
+//!    };
+//!    let form = bucket
+//!        .post_policy(&conditions, Duration::from_secs(900))
+//!        .await
+//!        .unwrap();
+//!    // post the fields, a "key" starting with uploads/ and the "file"
+//!    println!("{} {:?}", form.url, form.fields);
+//!}
+//!```
+//!
+//! Write blobs with the cache headers and metadata a CDN serves them with
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "aws")]
+//!async fn test_write_options() {
+//!    use bytes::Bytes;
+//!    use waihona::types::blob::WriteOptions;
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
+//!    let bucket = aws_buckets.open("waihona").await.unwrap();
+//!    let mut options = WriteOptions {
+//!        content_type: Some("text/css".to_owned()),
+//!        cache_control: Some("public, max-age=31536000, immutable".to_owned()),
+//!        ..Default::default()
+//!    };
+//!    options.metadata.insert("release".to_owned(), "v2".to_owned());
+//!    bucket
+//!        .write_blob_with_options(
+//!            "static/site.css",
+//!            Some(Bytes::from("body { margin: 0 }")),
+//!            &options,
+//!        )
+//!        .await
+//!        .unwrap();
+//!}
+//!```
+//!
+//! Relabel a large blob without downloading or uploading its content again
+//!
+//!```no_run
+//!#[tokio::test]
+//!#[cfg(feature = "gcp")]
+//!async fn test_set_properties() {
+//!    use waihona::types::blob::{Blob, WriteOptions};
+//!    use waihona::types::bucket::{Buckets, Bucket};
+//!    use waihona::providers;
+//!    let mut gcp_buckets = providers::gcp::GcpBuckets::new("waihona").unwrap();
+//!    let bucket = gcp_buckets.open("waihona").await.unwrap();
+//!    let mut blob = bucket.stat_blob("backups/2021-06.tar.gz").await.unwrap();
+//!    let mut options = WriteOptions {
+//!        content_type: blob.properties().content_type.clone(),
+//!        ..Default::default()
+//!    };
+//!    options.metadata.insert("retention".to_owned(), "archive".to_owned());
+//!    blob.set_properties(&options).await.unwrap();
+//!}
+//!```
 
 pub mod providers;
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local"
+))]
+pub use providers::location::open_url;
 #[cfg(test)]
 pub mod tests;
 pub mod types;
@@ -0,0 +1,517 @@
+//! Provider agnostic wrappers over every enabled provider.
+//! They allow picking a provider at runtime e.g from configuration
+//! while still using the Buckets, Bucket and Blob traits
+
+#[cfg(feature = "aws")]
+use crate::providers::aws::{AwsBlob, AwsBucket, AwsBuckets};
+#[cfg(feature = "azure")]
+use crate::providers::azure::{AzureBlob, AzureBucket, AzureBuckets};
+#[cfg(feature = "gcp")]
+use crate::providers::gcp::{GcpBlob, GcpBucket, GcpBuckets};
+#[cfg(feature = "local")]
+use crate::providers::local::{LocalBlob, LocalBucket, LocalBuckets};
+#[cfg(feature = "memory")]
+use crate::providers::memory::{MemoryBlob, MemoryBucket, MemoryBuckets};
+use crate::types::blob::{
+    Blob, BlobProperties, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
+use crate::types::errors::{
+    BlobResult, BucketResult, ProviderError, ProviderResult,
+};
+use crate::types::retry::RetryPolicy;
+use crate::types::timeout::TimeoutConfig;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Forward a call to whichever provider the wrapper holds
+macro_rules! dispatch {
+    ($value:expr, $wrapper:ident, $inner:pat => $body:expr) => {
+        match $value {
+            #[cfg(feature = "aws")]
+            $wrapper::Aws($inner) => $body,
+            #[cfg(feature = "gcp")]
+            $wrapper::Gcp($inner) => $body,
+            #[cfg(feature = "azure")]
+            $wrapper::Azure($inner) => $body,
+            #[cfg(feature = "local")]
+            $wrapper::Local($inner) => $body,
+            #[cfg(feature = "memory")]
+            $wrapper::Memory($inner) => $body,
+        }
+    };
+}
+
+/// Provider behind a wrapper
+macro_rules! provider_of {
+    ($value:expr, $wrapper:ident) => {
+        match $value {
+            #[cfg(feature = "aws")]
+            $wrapper::Aws(_) => Provider::Aws,
+            #[cfg(feature = "gcp")]
+            $wrapper::Gcp(_) => Provider::Gcp,
+            #[cfg(feature = "azure")]
+            $wrapper::Azure(_) => Provider::Azure,
+            #[cfg(feature = "local")]
+            $wrapper::Local(_) => Provider::Local,
+            #[cfg(feature = "memory")]
+            $wrapper::Memory(_) => Provider::Memory,
+        }
+    };
+}
+
+/// Providers enabled for this build
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    #[cfg(feature = "aws")]
+    Aws,
+    #[cfg(feature = "gcp")]
+    Gcp,
+    #[cfg(feature = "azure")]
+    Azure,
+    #[cfg(feature = "local")]
+    Local,
+    #[cfg(feature = "memory")]
+    Memory,
+}
+
+impl FromStr for Provider {
+    type Err = ProviderError;
+
+    /// Parse a provider name e.g "aws", "gcp", "azure", "local" or "memory".
+    /// Providers whose feature is disabled are not found
+    fn from_str(name: &str) -> ProviderResult<Self> {
+        match name.to_lowercase().as_str() {
+            #[cfg(feature = "aws")]
+            "aws" | "s3" => Ok(Provider::Aws),
+            #[cfg(feature = "gcp")]
+            "gcp" | "gcs" => Ok(Provider::Gcp),
+            #[cfg(feature = "azure")]
+            "azure" => Ok(Provider::Azure),
+            #[cfg(feature = "local")]
+            "local" => Ok(Provider::Local),
+            #[cfg(feature = "memory")]
+            "memory" => Ok(Provider::Memory),
+            _ => Err(ProviderError::NotFound),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            #[cfg(feature = "aws")]
+            Provider::Aws => "aws",
+            #[cfg(feature = "gcp")]
+            Provider::Gcp => "gcp",
+            #[cfg(feature = "azure")]
+            Provider::Azure => "azure",
+            #[cfg(feature = "local")]
+            Provider::Local => "local",
+            #[cfg(feature = "memory")]
+            Provider::Memory => "memory",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Buckets of any enabled provider
+pub enum AnyBuckets {
+    #[cfg(feature = "aws")]
+    Aws(AwsBuckets),
+    #[cfg(feature = "gcp")]
+    Gcp(GcpBuckets),
+    #[cfg(feature = "azure")]
+    Azure(AzureBuckets),
+    #[cfg(feature = "local")]
+    Local(LocalBuckets),
+    #[cfg(feature = "memory")]
+    Memory(MemoryBuckets),
+}
+
+impl AnyBuckets {
+    /// Create buckets for provider, location is what the provider's
+    /// own constructor expects:
+    /// region on aws, project name on gcp, storage account on azure,
+    /// root directory on local and is ignored for memory
+    pub fn new(provider: Provider, location: &str) -> BucketResult<AnyBuckets> {
+        let buckets = match provider {
+            #[cfg(feature = "aws")]
+            Provider::Aws => AwsBuckets::new(location)?.into(),
+            #[cfg(feature = "gcp")]
+            Provider::Gcp => GcpBuckets::new(location)?.into(),
+            #[cfg(feature = "azure")]
+            Provider::Azure => AzureBuckets::new(location.to_owned())?.into(),
+            #[cfg(feature = "local")]
+            Provider::Local => LocalBuckets::new(location).into(),
+            #[cfg(feature = "memory")]
+            Provider::Memory => {
+                let _ = location;
+                MemoryBuckets::new().into()
+            }
+        };
+        Ok(buckets)
+    }
+
+    /// Provider these buckets belong to
+    pub fn provider(&self) -> Provider {
+        provider_of!(*self, AnyBuckets)
+    }
+
+    /// Set how failed requests are retried, local and memory
+    /// buckets make no requests and ignore it
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        let _ = policy;
+        match self {
+            #[cfg(feature = "aws")]
+            AnyBuckets::Aws(b) => b.with_retry_policy(policy).into(),
+            #[cfg(feature = "gcp")]
+            AnyBuckets::Gcp(b) => b.with_retry_policy(policy).into(),
+            #[cfg(feature = "azure")]
+            AnyBuckets::Azure(b) => b.with_retry_policy(policy).into(),
+            #[cfg(feature = "local")]
+            AnyBuckets::Local(b) => b.into(),
+            #[cfg(feature = "memory")]
+            AnyBuckets::Memory(b) => b.into(),
+        }
+    }
+
+    /// Set how long requests may take, local and memory
+    /// buckets make no requests and ignore it
+    pub fn with_timeout_config(self, config: TimeoutConfig) -> Self {
+        let _ = config;
+        match self {
+            #[cfg(feature = "aws")]
+            AnyBuckets::Aws(b) => b.with_timeout_config(config).into(),
+            #[cfg(feature = "gcp")]
+            AnyBuckets::Gcp(b) => b.with_timeout_config(config).into(),
+            #[cfg(feature = "azure")]
+            AnyBuckets::Azure(b) => b.with_timeout_config(config).into(),
+            #[cfg(feature = "local")]
+            AnyBuckets::Local(b) => b.into(),
+            #[cfg(feature = "memory")]
+            AnyBuckets::Memory(b) => b.into(),
+        }
+    }
+}
+
+/// Bucket of any enabled provider
+#[derive(Clone)]
+pub enum AnyBucket {
+    #[cfg(feature = "aws")]
+    Aws(AwsBucket),
+    #[cfg(feature = "gcp")]
+    Gcp(GcpBucket),
+    #[cfg(feature = "azure")]
+    Azure(AzureBucket),
+    #[cfg(feature = "local")]
+    Local(LocalBucket),
+    #[cfg(feature = "memory")]
+    Memory(MemoryBucket),
+}
+
+impl AnyBucket {
+    /// Provider this bucket belongs to
+    pub fn provider(&self) -> Provider {
+        provider_of!(*self, AnyBucket)
+    }
+
+    /// Set how long requests of this bucket and its blobs may take,
+    /// local and memory buckets make no requests and ignore it
+    pub fn with_timeout_config(self, config: TimeoutConfig) -> Self {
+        let _ = config;
+        match self {
+            #[cfg(feature = "aws")]
+            AnyBucket::Aws(b) => b.with_timeout_config(config).into(),
+            #[cfg(feature = "gcp")]
+            AnyBucket::Gcp(b) => b.with_timeout_config(config).into(),
+            #[cfg(feature = "azure")]
+            AnyBucket::Azure(b) => b.with_timeout_config(config).into(),
+            #[cfg(feature = "local")]
+            AnyBucket::Local(b) => b.into(),
+            #[cfg(feature = "memory")]
+            AnyBucket::Memory(b) => b.into(),
+        }
+    }
+}
+
+/// Blob of any enabled provider
+#[derive(Debug)]
+pub enum AnyBlob {
+    #[cfg(feature = "aws")]
+    Aws(AwsBlob),
+    #[cfg(feature = "gcp")]
+    Gcp(GcpBlob),
+    #[cfg(feature = "azure")]
+    Azure(AzureBlob),
+    #[cfg(feature = "local")]
+    Local(LocalBlob),
+    #[cfg(feature = "memory")]
+    Memory(MemoryBlob),
+}
+
+impl AnyBlob {
+    /// Provider this blob belongs to
+    pub fn provider(&self) -> Provider {
+        provider_of!(*self, AnyBlob)
+    }
+}
+
+/// Implement From for every provider's type into its wrapper
+macro_rules! impl_from {
+    ($wrapper:ident, $variant:ident, $inner:ty) => {
+        impl From<$inner> for $wrapper {
+            fn from(inner: $inner) -> Self {
+                $wrapper::$variant(inner)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "aws")]
+impl_from!(AnyBuckets, Aws, AwsBuckets);
+#[cfg(feature = "aws")]
+impl_from!(AnyBucket, Aws, AwsBucket);
+#[cfg(feature = "aws")]
+impl_from!(AnyBlob, Aws, AwsBlob);
+#[cfg(feature = "gcp")]
+impl_from!(AnyBuckets, Gcp, GcpBuckets);
+#[cfg(feature = "gcp")]
+impl_from!(AnyBucket, Gcp, GcpBucket);
+#[cfg(feature = "gcp")]
+impl_from!(AnyBlob, Gcp, GcpBlob);
+#[cfg(feature = "azure")]
+impl_from!(AnyBuckets, Azure, AzureBuckets);
+#[cfg(feature = "azure")]
+impl_from!(AnyBucket, Azure, AzureBucket);
+#[cfg(feature = "azure")]
+impl_from!(AnyBlob, Azure, AzureBlob);
+#[cfg(feature = "local")]
+impl_from!(AnyBuckets, Local, LocalBuckets);
+#[cfg(feature = "local")]
+impl_from!(AnyBucket, Local, LocalBucket);
+#[cfg(feature = "local")]
+impl_from!(AnyBlob, Local, LocalBlob);
+#[cfg(feature = "memory")]
+impl_from!(AnyBuckets, Memory, MemoryBuckets);
+#[cfg(feature = "memory")]
+impl_from!(AnyBucket, Memory, MemoryBucket);
+#[cfg(feature = "memory")]
+impl_from!(AnyBlob, Memory, MemoryBlob);
+
+#[async_trait]
+impl Blob for AnyBlob {
+    fn properties(&self) -> &BlobProperties {
+        dispatch!(*self, AnyBlob, ref b => b.properties())
+    }
+
+    fn content_range(&self) -> Option<&str> {
+        dispatch!(*self, AnyBlob, ref b => b.content_range())
+    }
+
+    async fn delete(&self) -> BlobResult<bool> {
+        dispatch!(*self, AnyBlob, ref b => b.delete().await)
+    }
+
+    async fn copy(
+        &self,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<bool> {
+        dispatch!(*self, AnyBlob, ref b => {
+            b.copy(blob_destination_path, content_type).await
+        })
+    }
+
+    async fn write_with_options(
+        &self,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<bool> {
+        dispatch!(*self, AnyBlob, ref b => {
+            b.write_with_options(content, options).await
+        })
+    }
+
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()> {
+        dispatch!(*self, AnyBlob, ref mut b => b.set_properties(options).await)
+    }
+
+    async fn read(&mut self) -> BlobResult<Bytes> {
+        dispatch!(*self, AnyBlob, ref mut b => b.read().await)
+    }
+
+    async fn read_stream(&mut self) -> BlobResult<BlobStream> {
+        dispatch!(*self, AnyBlob, ref mut b => b.read_stream().await)
+    }
+
+    async fn signed_url(
+        &self,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        dispatch!(*self, AnyBlob, ref b => {
+            b.signed_url(method, expires_in).await
+        })
+    }
+}
+
+#[async_trait]
+impl Bucket<AnyBlob> for AnyBucket {
+    async fn list_blobs(
+        &self,
+        marker: Option<String>,
+    ) -> BucketResult<(Vec<AnyBlob>, Option<String>)> {
+        dispatch!(*self, AnyBucket, ref b => {
+            let (blobs, marker) = b.list_blobs(marker).await?;
+            Ok((blobs.into_iter().map(AnyBlob::from).collect(), marker))
+        })
+    }
+
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<AnyBlob>> {
+        dispatch!(*self, AnyBucket, ref b => {
+            let list = b.list_blobs_with_options(options).await?;
+            Ok(BlobList {
+                blobs: list.blobs.into_iter().map(AnyBlob::from).collect(),
+                prefixes: list.prefixes,
+                marker: list.marker,
+            })
+        })
+    }
+
+    async fn get_blob(
+        &self,
+        blob_path: &str,
+        range: Option<ByteRange>,
+    ) -> BlobResult<AnyBlob> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.get_blob(blob_path, range).await.map(AnyBlob::from)
+        })
+    }
+
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<AnyBlob> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.stat_blob(blob_path).await.map(AnyBlob::from)
+        })
+    }
+
+    async fn copy_blob(
+        &self,
+        blob_path: &str,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<AnyBlob> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.copy_blob(blob_path, blob_destination_path, content_type)
+                .await
+                .map(AnyBlob::from)
+        })
+    }
+
+    async fn write_blob_with_options(
+        &self,
+        blob_name: &str,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<AnyBlob> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.write_blob_with_options(blob_name, content, options)
+                .await
+                .map(AnyBlob::from)
+        })
+    }
+
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<AnyBlob> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.write_blob_stream_with_options(blob_name, content, options)
+                .await
+                .map(AnyBlob::from)
+        })
+    }
+
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<AnyBlob> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.update_blob_metadata(blob_path, options)
+                .await
+                .map(AnyBlob::from)
+        })
+    }
+
+    async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
+        dispatch!(*self, AnyBucket, ref b => b.delete_blob(blob_path).await)
+    }
+
+    async fn signed_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.signed_url(blob_path, method, expires_in).await
+        })
+    }
+
+    async fn post_policy(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        dispatch!(*self, AnyBucket, ref b => {
+            b.post_policy(conditions, expires_in).await
+        })
+    }
+}
+
+#[async_trait]
+impl Buckets<AnyBucket, AnyBlob> for AnyBuckets {
+    async fn open(&mut self, bucket_name: &str) -> BucketResult<AnyBucket> {
+        dispatch!(*self, AnyBuckets, ref mut b => {
+            b.open(bucket_name).await.map(AnyBucket::from)
+        })
+    }
+
+    async fn create(
+        &mut self,
+        bucket_name: &str,
+        location: Option<String>,
+    ) -> BucketResult<AnyBucket> {
+        dispatch!(*self, AnyBuckets, ref mut b => {
+            b.create(bucket_name, location).await.map(AnyBucket::from)
+        })
+    }
+
+    async fn list(&mut self) -> BucketResult<Vec<AnyBucket>> {
+        dispatch!(*self, AnyBuckets, ref mut b => {
+            Ok(b.list().await?.into_iter().map(AnyBucket::from).collect())
+        })
+    }
+
+    async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        dispatch!(*self, AnyBuckets, ref mut b => b.delete(bucket_name).await)
+    }
+
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        dispatch!(*self, AnyBuckets, ref mut b => b.exists(bucket_name).await)
+    }
+}
@@ -1,25 +1,425 @@
-use crate::types::bucket::{Bucket, Buckets};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
-use regex::Regex;
+use chrono::{DateTime, Utc};
 //use futures::{StreamExt, TryStreamExt};
-use crate::types::blob::Blob;
-use crate::types::errors::{BlobError, BlobResult, BucketError, BucketResult};
-use futures::TryStreamExt;
-use rusoto_core::Region;
+use crate::providers::chunked;
+use crate::providers::{
+    apply_options, check_expiry, hex, post_policy_document, split_destination,
+};
+use crate::types::blob::{
+    Blob, BlobProperties, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::errors::{
+    BlobError, BlobResult, BucketError, BucketResult, ErrorDetails, ErrorKind,
+};
+use crate::types::retry::RetryPolicy;
+use crate::types::timeout::TimeoutConfig;
+use bytes::BytesMut;
+use futures::{StreamExt, TryStreamExt};
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use percent_encoding::utf8_percent_encode;
+use ring::{digest, hmac};
+use rusoto_core::credential::{
+    AwsCredentials, CredentialsError, DefaultCredentialsProvider,
+    ProvideAwsCredentials, StaticProvider,
+};
+use rusoto_core::signature::{STRICT_ENCODE_SET, STRICT_PATH_ENCODE_SET};
+use rusoto_core::{HttpClient, Region, RusotoError};
 use rusoto_s3::{
-    CopyObjectRequest, CreateBucketConfiguration, CreateBucketRequest,
-    DeleteBucketRequest, DeleteObjectRequest, GetObjectRequest,
-    ListObjectsRequest, PutObjectRequest, S3Client, StreamingBody, S3,
+    AbortMultipartUploadError, AbortMultipartUploadRequest,
+    CompleteMultipartUploadError, CompleteMultipartUploadRequest,
+    CompletedMultipartUpload, CompletedPart, CopyObjectError,
+    CopyObjectRequest, CreateBucketConfiguration, CreateBucketError,
+    CreateBucketRequest, CreateMultipartUploadError,
+    CreateMultipartUploadRequest, DeleteBucketError, DeleteBucketRequest,
+    DeleteObjectError, DeleteObjectRequest, GetObjectError, GetObjectRequest,
+    HeadBucketError, HeadBucketRequest, HeadObjectError, HeadObjectRequest,
+    ListBucketsError, ListMultipartUploadsError, ListMultipartUploadsRequest,
+    ListObjectsError, ListObjectsRequest, PutObjectError, PutObjectRequest,
+    S3Client, StreamingBody, UploadPartError, UploadPartRequest, S3,
 };
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Timestamps come as RFC 2822 in headers and RFC 3339 in listings
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .or_else(|_| DateTime::parse_from_rfc3339(value))
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// x-amz-copy-source of key in bucket, the key is percent encoded
+/// as request paths are when signed
+fn copy_source(bucket: &str, key: &str) -> String {
+    format!(
+        "{}/{}",
+        bucket,
+        utf8_percent_encode(key, &STRICT_PATH_ENCODE_SET)
+    )
+}
+
+/// Metadata sent as x-amz-meta-* headers, None when there is none
+fn user_metadata(
+    metadata: HashMap<String, String>,
+) -> Option<HashMap<String, String>> {
+    if metadata.is_empty() {
+        None
+    } else {
+        Some(metadata)
+    }
+}
+
+/// Kind and status of the errors S3 operations define, rusoto only
+/// parses the codes below, other failures are classified by status
+trait ServiceErrorKind {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+
+    /// Status S3 responds to the error code with
+    fn status(&self) -> Option<u16> {
+        None
+    }
+}
+
+impl ServiceErrorKind for AbortMultipartUploadError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            AbortMultipartUploadError::NoSuchUpload(_) => ErrorKind::NotFound,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            AbortMultipartUploadError::NoSuchUpload(_) => Some(404),
+        }
+    }
+}
+
+impl ServiceErrorKind for CopyObjectError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            CopyObjectError::ObjectNotInActiveTierError(_) => {
+                ErrorKind::InvalidArgument
+            }
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            CopyObjectError::ObjectNotInActiveTierError(_) => Some(403),
+        }
+    }
+}
+
+impl ServiceErrorKind for CreateBucketError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            CreateBucketError::BucketAlreadyExists(_)
+            | CreateBucketError::BucketAlreadyOwnedByYou(_) => {
+                ErrorKind::Conflict
+            }
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            CreateBucketError::BucketAlreadyExists(_)
+            | CreateBucketError::BucketAlreadyOwnedByYou(_) => Some(409),
+        }
+    }
+}
+
+impl ServiceErrorKind for GetObjectError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            GetObjectError::NoSuchKey(_) => ErrorKind::NotFound,
+            GetObjectError::InvalidObjectState(_) => ErrorKind::InvalidArgument,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            GetObjectError::NoSuchKey(_) => Some(404),
+            GetObjectError::InvalidObjectState(_) => Some(403),
+        }
+    }
+}
+
+impl ServiceErrorKind for HeadBucketError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            HeadBucketError::NoSuchBucket(_) => ErrorKind::NotFound,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            HeadBucketError::NoSuchBucket(_) => Some(404),
+        }
+    }
+}
+
+impl ServiceErrorKind for HeadObjectError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            HeadObjectError::NoSuchKey(_) => ErrorKind::NotFound,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            HeadObjectError::NoSuchKey(_) => Some(404),
+        }
+    }
+}
+
+impl ServiceErrorKind for ListObjectsError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ListObjectsError::NoSuchBucket(_) => ErrorKind::NotFound,
+        }
+    }
+
+    fn status(&self) -> Option<u16> {
+        match self {
+            ListObjectsError::NoSuchBucket(_) => Some(404),
+        }
+    }
+}
+
+impl ServiceErrorKind for CompleteMultipartUploadError {}
+impl ServiceErrorKind for CreateMultipartUploadError {}
+impl ServiceErrorKind for DeleteBucketError {}
+impl ServiceErrorKind for DeleteObjectError {}
+impl ServiceErrorKind for ListBucketsError {}
+impl ServiceErrorKind for ListMultipartUploadsError {}
+impl ServiceErrorKind for PutObjectError {}
+impl ServiceErrorKind for UploadPartError {}
+
+impl<E> From<RusotoError<E>> for ErrorDetails
+where
+    E: ServiceErrorKind + std::error::Error + Send + Sync + 'static,
+{
+    fn from(error: RusotoError<E>) -> Self {
+        let details = match &error {
+            RusotoError::Service(e) => {
+                let details = ErrorDetails::new(e.kind(), e.to_string());
+                match e.status() {
+                    Some(status) => details.with_status(status),
+                    None => details,
+                }
+            }
+            RusotoError::Unknown(resp) => {
+                let details = ErrorDetails::from_status(
+                    resp.status.as_u16(),
+                    resp.body_as_str(),
+                );
+                match resp.headers.get("x-amz-request-id") {
+                    Some(request_id) => details.with_request_id(request_id),
+                    None => details,
+                }
+            }
+            RusotoError::Validation(message) => {
+                ErrorDetails::new(ErrorKind::InvalidArgument, message.as_str())
+            }
+            RusotoError::HttpDispatch(e) => {
+                ErrorDetails::new(ErrorKind::Connection, e.to_string())
+            }
+            RusotoError::Credentials(e) => {
+                ErrorDetails::new(ErrorKind::PermissionDenied, e.to_string())
+            }
+            e => ErrorDetails::new(ErrorKind::Other, e.to_string()),
+        };
+        details.with_source(error)
+    }
+}
+
+/// Smallest part S3 accepts for all but the last part of an upload
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// Most parts S3 accepts in an upload
+const MAX_PARTS: usize = 10_000;
+
+/// Controls when and how blobs are uploaded in parts
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartConfig {
+    /// Size of each part, raised to 5MiB if lower.
+    /// An upload has at most 10000 parts, so this also
+    /// bounds the size of blobs, larger ones fail with
+    /// InvalidArgument before their 10001st part is sent
+    pub part_size: usize,
+    /// Number of parts uploaded at the same time
+    pub concurrency: usize,
+    /// Blobs larger than this are uploaded in parts
+    pub threshold: usize,
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        MultipartConfig {
+            part_size: 8 * 1024 * 1024,
+            concurrency: 4,
+            threshold: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Multipart upload that was started but neither completed nor aborted
+#[derive(Debug, Clone)]
+pub struct PendingUpload {
+    pub key: String,
+    pub upload_id: String,
+    /// When the upload was started
+    pub initiated: Option<DateTime<Utc>>,
+}
+
+/// Outcome of aborting stale multipart uploads
+#[derive(Debug, Default)]
+pub struct AbortedUploads {
+    pub aborted: Vec<PendingUpload>,
+    /// Uploads whose abort failed, with the reason
+    pub failed: Vec<(PendingUpload, BlobError)>,
+}
 
 pub struct AwsBuckets {
     s3: S3Client,
+    region: Region,
+    /// None uses rusoto's default chain
+    credentials: Option<AwsCredentials>,
+    multipart: MultipartConfig,
+    retry: RetryPolicy,
+    timeouts: TimeoutConfig,
 }
 
+#[derive(Clone)]
 pub struct AwsBucket {
     name: String,
     s3: S3Client,
+    /// Region urls and forms are signed for,
+    /// None when it is unknown and they cannot be
+    region: Option<Region>,
+    /// Credentials urls and forms are signed with,
+    /// None uses rusoto's default chain
+    credentials: Option<AwsCredentials>,
+    multipart: MultipartConfig,
+    retry: RetryPolicy,
+    timeouts: TimeoutConfig,
+}
+
+/// Builds an AwsBuckets for a region, with the credentials given
+/// instead of the ones of rusoto's default chain: environment,
+/// profile file, then container and instance metadata
+#[derive(Debug, Clone, Default)]
+pub struct AwsBucketsBuilder {
+    region: Option<String>,
+    endpoint: Option<String>,
+    credentials: Option<AwsCredentials>,
+}
+
+impl AwsBucketsBuilder {
+    /// Region of the client, us-east-1 when left out
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Endpoint of an S3 compatible store such as MinIO, Ceph RGW,
+    /// Wasabi or LocalStack e.g "http://localhost:9000".
+    /// Buckets are addressed in the path, region is then only
+    /// used to sign requests and may be any name the store accepts
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Access key pair the client signs its requests with
+    pub fn credentials(
+        mut self,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some(AwsCredentials::new(
+            access_key_id,
+            secret_access_key,
+            None,
+            None,
+        ));
+        self
+    }
+
+    /// Session token of temporary credentials e.g from STS,
+    /// set after credentials
+    pub fn session_token(mut self, token: impl Into<String>) -> Self {
+        if let Some(credentials) = self.credentials.take() {
+            self.credentials = Some(AwsCredentials::new(
+                credentials.aws_access_key_id(),
+                credentials.aws_secret_access_key(),
+                Some(token.into()),
+                None,
+            ));
+        }
+        self
+    }
+
+    /// Fails on unknown regions when no endpoint is set
+    pub fn build(self) -> BucketResult<AwsBuckets> {
+        let name = self.region.as_deref().unwrap_or("us-east-1");
+        let region = match self.endpoint {
+            Some(ref endpoint) => Region::Custom {
+                name: name.to_owned(),
+                endpoint: endpoint.trim_end_matches('/').to_owned(),
+            },
+            None => string_to_region(name)?,
+        };
+        Ok(AwsBuckets {
+            s3: s3_client(&region, self.credentials.as_ref(), None),
+            region,
+            credentials: self.credentials,
+            multipart: MultipartConfig::default(),
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
+        })
+    }
+}
+
+/// S3 client signing with credentials, else with the default chain.
+/// A connect timeout needs a client of its own, the others share one
+fn s3_client(
+    region: &Region,
+    credentials: Option<&AwsCredentials>,
+    connect: Option<Duration>,
+) -> S3Client {
+    let dispatcher = || {
+        let mut http = HttpConnector::new();
+        http.set_connect_timeout(connect);
+        http.enforce_http(false);
+        HttpClient::from_connector(HttpsConnector::new_with_connector(http))
+    };
+    match (credentials, connect) {
+        (Some(credentials), _) => S3Client::new_with(
+            dispatcher(),
+            StaticProvider::from(credentials.clone()),
+            region.clone(),
+        ),
+        (None, Some(_)) => match DefaultCredentialsProvider::new() {
+            Ok(provider) => {
+                S3Client::new_with(dispatcher(), provider, region.clone())
+            }
+            // only fails in name, credentials are loaded on first use
+            Err(_) => S3Client::new(region.clone()),
+        },
+        (None, None) => S3Client::new(region.clone()),
+    }
+}
+
+/// Credentials of rusoto's default chain
+async fn default_credentials() -> Result<AwsCredentials, CredentialsError> {
+    DefaultCredentialsProvider::new()?.credentials().await
 }
 
 pub fn string_to_region(reg: &str) -> BucketResult<Region> {
@@ -49,31 +449,576 @@ pub fn string_to_region(reg: &str) -> BucketResult<Region> {
         "cn-north-1" => Ok(Region::CnNorth1),
         "cn-northwest-1" => Ok(Region::CnNorthwest1),
         "af-south-1" => Ok(Region::AfSouth1),
-        _ => Err(BucketError::NotFound),
+        _ => Err(BucketError::OpenError(ErrorDetails::new(
+            ErrorKind::InvalidArgument,
+            format!("Unknown region {}", reg),
+        ))),
     }
 }
 
 impl AwsBucket {
+    /// Without a client one is made for us-east-2. Urls and forms are
+    /// signed with rusoto's default credentials once a region is set
+    /// with with_region. Buckets opened from AwsBuckets use its region
+    /// and credentials
     pub fn new(name: String, s3: Option<S3Client>) -> Self {
-        match s3 {
-            Some(a) => AwsBucket { name, s3: a },
-            None => AwsBucket {
-                name,
-                s3: S3Client::new(Region::UsEast2),
+        AwsBucket {
+            name,
+            s3: s3.unwrap_or_else(|| S3Client::new(Region::UsEast2)),
+            region: None,
+            credentials: None,
+            multipart: MultipartConfig::default(),
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
+        }
+    }
+
+    /// Set the region urls and forms are signed for, it must be
+    /// the one the client was made for
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Set how blobs are uploaded in parts
+    pub fn with_multipart_config(mut self, config: MultipartConfig) -> Self {
+        self.multipart = config;
+        self
+    }
+
+    /// Region to sign for, signing fails without one rather
+    /// than producing urls S3 refuses
+    fn signing_region(&self) -> BlobResult<&Region> {
+        self.region.as_ref().ok_or_else(|| {
+            BlobError::SignError(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Region of bucket {} is unknown, set it with with_region",
+                    self.name
+                ),
+            ))
+        })
+    }
+
+    /// Set how failed requests of this bucket and its blobs are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set how long requests of this bucket and its blobs may take,
+    /// TimeoutConfig::scope gives a single call its own
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        self.timeouts = config;
+        self
+    }
+
+    /// Credentials urls and forms are signed with
+    async fn signing_credentials(&self) -> BlobResult<AwsCredentials> {
+        match self.credentials {
+            Some(ref credentials) => Ok(credentials.clone()),
+            None => default_credentials().await.map_err(|e| {
+                BlobError::SignError(ErrorDetails::from_source(
+                    ErrorKind::PermissionDenied,
+                    e,
+                ))
+            }),
+        }
+    }
+
+    /// Blob written with options, built from the etag the write
+    /// returned so the blob need not be read back. S3 lowercases
+    /// metadata keys, its last modified time is not known
+    fn written(
+        &self,
+        blob_path: &str,
+        e_tag: Option<String>,
+        size: u64,
+        options: WriteOptions,
+    ) -> AwsBlob {
+        let mut blob = AwsBlob::new(
+            Some(blob_path.to_owned()),
+            e_tag,
+            Some(size as i64),
+            None,
+            None,
+            None,
+            self.name.clone(),
+        );
+        apply_options(&mut blob.properties, options);
+        blob.properties.metadata = blob
+            .properties
+            .metadata
+            .drain()
+            .map(|(key, value)| (key.to_lowercase(), value))
+            .collect();
+        blob.bucket = Some(self.clone());
+        blob.retry = self.retry;
+        blob.timeouts = self.timeouts;
+        blob
+    }
+
+    /// Url forms are posted to, the bucket is addressed in the path
+    fn post_url(&self, region: &Region) -> String {
+        match region {
+            Region::Custom { endpoint, .. } => {
+                let endpoint = endpoint.trim_end_matches('/');
+                if endpoint.contains("://") {
+                    format!("{}/{}", endpoint, self.name)
+                } else {
+                    format!("https://{}/{}", endpoint, self.name)
+                }
+            }
+            region if region.name().starts_with("cn-") => format!(
+                "https://s3.{}.amazonaws.com.cn/{}",
+                region.name(),
+                self.name
+            ),
+            region => format!(
+                "https://s3.{}.amazonaws.com/{}",
+                region.name(),
+                self.name
+            ),
+        }
+    }
+
+    /// Key signing requests of date in the region, derived from the
+    /// secret for the scope
+    fn signing_key(
+        region: &Region,
+        credentials: &AwsCredentials,
+        date: &str,
+    ) -> hmac::Key {
+        let secret = format!("AWS4{}", credentials.aws_secret_access_key());
+        [date, region.name(), "s3", "aws4_request"].iter().fold(
+            hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            |key, part| {
+                let tag = hmac::sign(&key, part.as_bytes());
+                hmac::Key::new(hmac::HMAC_SHA256, tag.as_ref())
             },
+        )
+    }
+
+    /// Url granting method on blob_path for expires_in from now, signed
+    /// with signature version 4 in the query string, see
+    /// https://docs.aws.amazon.com/AmazonS3/latest/API/sigv4-query-string-auth.html
+    pub(crate) async fn signed_url_at(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+        now: DateTime<Utc>,
+    ) -> BlobResult<String> {
+        check_expiry(expires_in)?;
+        let region = self.signing_region()?;
+        let credentials = self.signing_credentials().await?;
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, region.name());
+        let url = format!(
+            "{}/{}",
+            self.post_url(region),
+            utf8_percent_encode(blob_path, &STRICT_PATH_ENCODE_SET)
+        );
+        let (_, authority) = url.split_once("://").unwrap_or_default();
+        let (host, path) = authority.split_at(authority.find('/').unwrap_or(0));
+        let mut params = vec![
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_owned()),
+            (
+                "X-Amz-Credential",
+                format!("{}/{}", credentials.aws_access_key_id(), scope),
+            ),
+            ("X-Amz-Date", timestamp.clone()),
+            ("X-Amz-Expires", expires_in.as_secs().to_string()),
+            ("X-Amz-SignedHeaders", "host".to_owned()),
+        ];
+        if let Some(token) = credentials.token() {
+            params.push(("X-Amz-Security-Token", token.clone()));
+        }
+        params.sort();
+        let query = params
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{}={}",
+                    name,
+                    utf8_percent_encode(value, &STRICT_ENCODE_SET)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let request = format!(
+            "{}\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+            method, path, query, host
+        );
+        let hashed = digest::digest(&digest::SHA256, request.as_bytes());
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(hashed.as_ref())
+        );
+        let key = Self::signing_key(region, &credentials, &date);
+        let signature = hmac::sign(&key, string_to_sign.as_bytes());
+        Ok(format!(
+            "{}?{}&X-Amz-Signature={}",
+            url,
+            query,
+            hex(signature.as_ref())
+        ))
+    }
+
+    /// Presigned POST of uploads meeting conditions for expires_in
+    /// from now, signed with signature version 4, see
+    /// https://docs.aws.amazon.com/AmazonS3/latest/API/sigv4-HTTPPOSTConstructPolicy.html
+    pub(crate) async fn post_policy_at(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+        now: DateTime<Utc>,
+    ) -> BlobResult<PostForm> {
+        check_expiry(expires_in)?;
+        let region = self.signing_region()?;
+        let credentials = self.signing_credentials().await?;
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, region.name());
+        let mut fields = Vec::new();
+        if let Some(ref content_type) = conditions.content_type {
+            fields.push(("Content-Type".to_owned(), content_type.clone()));
+        }
+        fields.push((
+            "x-amz-algorithm".to_owned(),
+            "AWS4-HMAC-SHA256".to_owned(),
+        ));
+        fields.push((
+            "x-amz-credential".to_owned(),
+            format!("{}/{}", credentials.aws_access_key_id(), scope),
+        ));
+        fields.push((
+            "x-amz-date".to_owned(),
+            now.format("%Y%m%dT%H%M%SZ").to_string(),
+        ));
+        if let Some(token) = credentials.token() {
+            fields.push(("x-amz-security-token".to_owned(), token.clone()));
+        }
+        let policy = post_policy_document(
+            &self.name, conditions, &fields, now, expires_in,
+        );
+        let key = Self::signing_key(region, &credentials, &date);
+        let signature = hex(hmac::sign(&key, policy.as_bytes()).as_ref());
+        fields.push(("policy".to_owned(), policy));
+        fields.push(("x-amz-signature".to_owned(), signature));
+        Ok(PostForm {
+            url: self.post_url(region),
+            fields,
+        })
+    }
+
+    /// Upload content through a multipart upload whatever its size.
+    /// Parts are uploaded concurrently and the upload is aborted
+    /// if any of them fails
+    pub async fn write_blob_multipart(
+        &self,
+        blob_path: &str,
+        content: BlobStream,
+        content_type: Option<String>,
+    ) -> BlobResult<AwsBlob> {
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        self.write_blob_multipart_with_options(blob_path, content, &options)
+            .await
+    }
+
+    /// Upload content through a multipart upload with the headers
+    /// and metadata of options, see write_blob_multipart
+    pub async fn write_blob_multipart_with_options(
+        &self,
+        blob_path: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<AwsBlob> {
+        let written = options.clone();
+        let options = options.clone();
+        let create_req = CreateMultipartUploadRequest {
+            bucket: self.name.clone(),
+            key: blob_path.to_string(),
+            content_type: options.content_type,
+            cache_control: options.cache_control,
+            content_encoding: options.content_encoding,
+            content_disposition: options.content_disposition,
+            content_language: options.content_language,
+            storage_class: options.storage_class,
+            metadata: user_metadata(options.metadata),
+            ..Default::default()
+        };
+        let upload_id = match self
+            .timeouts
+            .send_once(async {
+                self.s3
+                    .create_multipart_upload(create_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await
+        {
+            Ok(k) => k.upload_id.unwrap_or_default(),
+            Err(e) => return Err(BlobError::WriteError(e)),
+        };
+        let mut size = 0;
+        let resp = match self.upload_parts(blob_path, &upload_id, content).await
+        {
+            Ok((parts, uploaded)) => {
+                size = uploaded;
+                let complete_req = CompleteMultipartUploadRequest {
+                    bucket: self.name.clone(),
+                    key: blob_path.to_string(),
+                    upload_id: upload_id.clone(),
+                    multipart_upload: Some(CompletedMultipartUpload {
+                        parts: Some(parts),
+                    }),
+                    ..Default::default()
+                };
+                self.timeouts
+                    .send_once(async {
+                        self.s3
+                            .complete_multipart_upload(complete_req)
+                            .await
+                            .map_err(ErrorDetails::from)
+                    })
+                    .await
+                    .map_err(BlobError::WriteError)
+            }
+            Err(e) => Err(e),
+        };
+        match resp {
+            Ok(k) => Ok(self.written(blob_path, k.e_tag, size, written)),
+            Err(e) => {
+                // parts already uploaded are billed until the upload
+                // is aborted
+                let _ =
+                    self.abort_multipart_upload(blob_path, &upload_id).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Upload content as parts of upload_id, returns
+    /// the parts to complete the upload with
+    async fn upload_parts(
+        &self,
+        blob_path: &str,
+        upload_id: &str,
+        content: BlobStream,
+    ) -> BlobResult<(Vec<CompletedPart>, u64)> {
+        let part_size = std::cmp::max(self.multipart.part_size, MIN_PART_SIZE);
+        let concurrency = std::cmp::max(self.multipart.concurrency, 1);
+        let uploaded: Vec<(CompletedPart, u64)> = chunked(content, part_size)
+            .enumerate()
+            .map(|(i, part)| async move {
+                if i >= MAX_PARTS {
+                    return Err(BlobError::WriteError(ErrorDetails::new(
+                        ErrorKind::InvalidArgument,
+                        format!(
+                            "Content needs more than {} parts of {} bytes",
+                            MAX_PARTS, part_size
+                        ),
+                    )));
+                }
+                let part = part?;
+                let size = part.len() as u64;
+                self.upload_part(blob_path, upload_id, i as i64 + 1, part)
+                    .await
+                    .map(|completed| (completed, size))
+            })
+            .buffer_unordered(concurrency)
+            .try_collect()
+            .await?;
+        let size = uploaded.iter().map(|(_, size)| size).sum();
+        let mut parts: Vec<CompletedPart> =
+            uploaded.into_iter().map(|(part, _)| part).collect();
+        // an upload needs at least one part, even if empty
+        if parts.is_empty() {
+            parts.push(
+                self.upload_part(blob_path, upload_id, 1, Bytes::new())
+                    .await?,
+            );
+        }
+        parts.sort_by_key(|part| part.part_number);
+        Ok((parts, size))
+    }
+
+    async fn upload_part(
+        &self,
+        blob_path: &str,
+        upload_id: &str,
+        part_number: i64,
+        part: Bytes,
+    ) -> BlobResult<CompletedPart> {
+        let part = &part;
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let upload_part_req = UploadPartRequest {
+                    bucket: self.name.clone(),
+                    key: blob_path.to_string(),
+                    upload_id: upload_id.to_string(),
+                    part_number,
+                    content_length: Some(part.len() as i64),
+                    body: Some(part.to_vec().into()),
+                    ..Default::default()
+                };
+                self.s3
+                    .upload_part(upload_part_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
+        match resp {
+            Ok(k) => Ok(CompletedPart {
+                e_tag: k.e_tag,
+                part_number: Some(part_number),
+            }),
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    /// List multipart uploads of this bucket that were never completed
+    /// nor aborted, e.g left behind by crashed processes.
+    /// Their parts are billed until they are aborted
+    pub async fn list_multipart_uploads(
+        &self,
+    ) -> BucketResult<Vec<PendingUpload>> {
+        let mut uploads = Vec::new();
+        let mut key_marker = None;
+        let mut upload_id_marker = None;
+        loop {
+            let markers = (&key_marker, &upload_id_marker);
+            let resp = self
+                .timeouts
+                .send(&self.retry, || async move {
+                    let (key_marker, upload_id_marker) = markers;
+                    let list_req = ListMultipartUploadsRequest {
+                        bucket: self.name.clone(),
+                        key_marker: key_marker.clone(),
+                        upload_id_marker: upload_id_marker.clone(),
+                        ..Default::default()
+                    };
+                    self.s3
+                        .list_multipart_uploads(list_req)
+                        .await
+                        .map_err(ErrorDetails::from)
+                })
+                .await
+                .map_err(BucketError::ListError)?;
+            for upload in resp.uploads.unwrap_or_default() {
+                if let (Some(key), Some(upload_id)) =
+                    (upload.key, upload.upload_id)
+                {
+                    uploads.push(PendingUpload {
+                        key,
+                        upload_id,
+                        initiated: upload
+                            .initiated
+                            .as_deref()
+                            .and_then(parse_time),
+                    });
+                }
+            }
+            if resp.is_truncated != Some(true) {
+                return Ok(uploads);
+            }
+            key_marker = resp.next_key_marker;
+            upload_id_marker = resp.next_upload_id_marker;
+        }
+    }
+
+    /// Abort the multipart uploads of this bucket started more than
+    /// older_than ago, leaving those that may still be in progress
+    /// and those whose start is unknown.
+    /// A failed abort does not stop the others, it is returned
+    /// along with the uploads that were aborted
+    pub async fn abort_stale_multipart_uploads(
+        &self,
+        older_than: Duration,
+    ) -> BlobResult<AbortedUploads> {
+        let older_than =
+            chrono::Duration::from_std(older_than).map_err(|e| {
+                BlobError::DeletionError(ErrorDetails::from_source(
+                    ErrorKind::InvalidArgument,
+                    e,
+                ))
+            })?;
+        let cutoff = Utc::now() - older_than;
+        let uploads = self
+            .list_multipart_uploads()
+            .await
+            .map_err(|e| BlobError::DeletionError(e.into()))?;
+        let mut outcome = AbortedUploads::default();
+        for upload in uploads {
+            if matches!(upload.initiated, Some(time) if time < cutoff) {
+                match self
+                    .abort_multipart_upload(&upload.key, &upload.upload_id)
+                    .await
+                {
+                    Ok(_) => outcome.aborted.push(upload),
+                    Err(e) => outcome.failed.push((upload, e)),
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Abort a multipart upload, deleting the parts uploaded so far
+    pub async fn abort_multipart_upload(
+        &self,
+        blob_path: &str,
+        upload_id: &str,
+    ) -> BlobResult<bool> {
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let abort_req = AbortMultipartUploadRequest {
+                    bucket: self.name.clone(),
+                    key: blob_path.to_string(),
+                    upload_id: upload_id.to_string(),
+                    ..Default::default()
+                };
+                self.s3
+                    .abort_multipart_upload(abort_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::DeletionError(e)),
         }
     }
 }
 
-#[derive(Debug)]
 pub struct AwsBlob {
-    key: Option<String>,
-    e_tag: Option<String>,
-    size: Option<i64>,
+    properties: BlobProperties,
     body: Option<StreamingBody>,
-    content_type: Option<String>,
     content_range: Option<String>,
-    bucket: String,
+    /// Bucket the blob comes from, a bucket of its own
+    /// when the blob was built through new
+    bucket: Option<AwsBucket>,
+    retry: RetryPolicy,
+    timeouts: TimeoutConfig,
+}
+
+impl std::fmt::Debug for AwsBlob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsBlob")
+            .field("properties", &self.properties)
+            .field("body", &self.body)
+            .field("content_range", &self.content_range)
+            .field("retry", &self.retry)
+            .field("timeouts", &self.timeouts)
+            .finish_non_exhaustive()
+    }
 }
 impl AwsBlob {
     pub fn new(
@@ -86,13 +1031,17 @@ impl AwsBlob {
         bucket: String,
     ) -> Self {
         AwsBlob {
-            key,
-            e_tag,
-            size,
+            properties: BlobProperties {
+                e_tag,
+                size: size.map(|size| size as u64),
+                content_type,
+                ..BlobProperties::new(key.unwrap_or_default(), bucket)
+            },
             body,
-            content_type,
             content_range,
-            bucket,
+            bucket: None,
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
         }
     }
 
@@ -100,28 +1049,35 @@ impl AwsBlob {
         region: &str,
         bucket: &str,
         blob_path: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<Self> {
-        let mut aws_buckets = AwsBuckets::new(region);
+        let mut aws_buckets = AwsBuckets::new(region)
+            .map_err(|e| BlobError::GetError(e.into()))?;
         let bucket_str = String::from(bucket);
         let bucket = aws_buckets.open(&bucket_str).await;
         match bucket {
-            Ok(b) => b.get_blob(blob_path, content_range).await,
-            Err(e) => Err(BlobError::GetError(e.to_string())),
+            Ok(b) => b.get_blob(blob_path, range).await,
+            Err(e) => Err(BlobError::GetError(e.into())),
         }
     }
 }
 
 #[async_trait]
 impl Blob for AwsBlob {
+    fn properties(&self) -> &BlobProperties {
+        &self.properties
+    }
+
+    fn content_range(&self) -> Option<&str> {
+        self.content_range.as_deref()
+    }
+
     async fn delete(&self) -> BlobResult<bool> {
-        let bucket = AwsBucket::new(self.bucket.clone(), None);
-        let resp = bucket.delete_blob(self.key.as_ref().unwrap()).await;
+        let bucket = self.bucket();
+        let resp = bucket.delete_blob(&self.properties.key).await;
         match resp {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::DeletionError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::DeletionError(e.into())),
         }
     }
 
@@ -130,64 +1086,190 @@ impl Blob for AwsBlob {
         blob_destination_path: &str,
         content_type: Option<String>,
     ) -> BlobResult<bool> {
-        let bucket = AwsBucket::new(self.bucket.clone(), None);
+        let bucket = self.bucket();
         let resp = bucket
             .copy_blob(
-                self.key.as_ref().unwrap(),
+                &self.properties.key,
                 blob_destination_path,
                 content_type,
             )
             .await;
         match resp {
             Ok(_) => Ok(true),
-            Err(e) => Err(BlobError::CopyError(String::from(format!("{}", e)))),
+            Err(e) => Err(BlobError::CopyError(e.into())),
         }
     }
 
-    async fn write(
+    async fn write_with_options(
         &self,
         content: Option<Bytes>,
-        content_type: Option<String>,
+        options: &WriteOptions,
     ) -> BlobResult<bool> {
-        let bucket = AwsBucket::new(self.bucket.clone(), None);
+        let bucket = self.bucket();
         let resp = bucket
-            .write_blob(self.key.as_ref().unwrap(), content, content_type)
+            .write_blob_with_options(&self.properties.key, content, options)
             .await;
         match resp {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::WriteError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::WriteError(e.into())),
         }
     }
 
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()> {
+        let bucket = self.bucket();
+        let updated = bucket
+            .update_blob_metadata(&self.properties.key, options)
+            .await?;
+        self.properties = updated.properties;
+        Ok(())
+    }
+
     async fn read(&mut self) -> BlobResult<Bytes> {
-        match self.body {
-            Some(ref mut res) => {
-                let body = res
-                    .map_ok(|b| bytes::BytesMut::from(&b[..]))
-                    .try_concat()
-                    .await
-                    .unwrap();
-                Ok(body.freeze())
-            }
-            None => Err(BlobError::ReadError),
+        let body = self
+            .body()
+            .await?
+            .map_ok(|b| bytes::BytesMut::from(&b[..]))
+            .try_concat()
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?;
+        Ok(body.freeze())
+    }
+
+    /// Streams the body returned by get_blob, which can
+    /// only be consumed once
+    async fn read_stream(&mut self) -> BlobResult<BlobStream> {
+        let body = self.body().await?;
+        Ok(Box::pin(body.map_err(|e| BlobError::ReadError(e.into()))))
+    }
+
+    async fn signed_url(
+        &self,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        self.bucket()
+            .signed_url(&self.properties.key, method, expires_in)
+            .await
+    }
+}
+
+impl AwsBlob {
+    fn bucket(&self) -> AwsBucket {
+        match self.bucket {
+            Some(ref bucket) => bucket.clone(),
+            None => AwsBucket::new(self.properties.bucket.clone(), None),
+        }
+        .with_retry_policy(self.retry)
+        .with_timeout_config(self.timeouts)
+    }
+
+    /// Body returned by get_blob, blobs obtained any other way
+    /// e.g through stat_blob or list_blobs download it
+    async fn body(&mut self) -> BlobResult<StreamingBody> {
+        if let Some(body) = self.body.take() {
+            return Ok(body);
         }
+        let bucket = self.bucket();
+        let mut blob = bucket
+            .get_blob(&self.properties.key, None)
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?;
+        blob.body.take().ok_or_else(|| {
+            BlobError::ReadError(ErrorDetails::new(
+                ErrorKind::Other,
+                "No body returned for blob",
+            ))
+        })
     }
 }
 
 impl AwsBuckets {
-    pub fn new(region: &str) -> Self {
-        let reg = string_to_region(region).unwrap();
-        AwsBuckets {
-            s3: S3Client::new(reg),
+    /// Fails on unknown regions
+    pub fn new(region: &str) -> BucketResult<Self> {
+        Self::builder().region(region).build()
+    }
+
+    /// Build a client with explicit credentials,
+    /// e.g AwsBuckets::builder().region("us-east-2").credentials(id, secret)
+    pub fn builder() -> AwsBucketsBuilder {
+        AwsBucketsBuilder::default()
+    }
+
+    /// Set how blobs of buckets opened from here are uploaded in parts
+    pub fn with_multipart_config(mut self, config: MultipartConfig) -> Self {
+        self.multipart = config;
+        self
+    }
+
+    /// Set how failed requests of this client, the buckets opened
+    /// from here and their blobs are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set how long requests of this client, the buckets opened
+    /// from here and their blobs may take.
+    /// A connect timeout replaces the client with one using it
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        if config.connect.is_some() {
+            self.s3 = s3_client(
+                &self.region,
+                self.credentials.as_ref(),
+                config.connect,
+            );
+        }
+        self.timeouts = config;
+        self
+    }
+
+    /// Bucket of this client without checking that it exists
+    pub(crate) fn bucket(&self, bucket_name: &str) -> AwsBucket {
+        AwsBucket {
+            credentials: self.credentials.clone(),
+            ..AwsBucket::new(bucket_name.to_string(), Some(self.s3.clone()))
+        }
+        .with_region(self.region.clone())
+        .with_multipart_config(self.multipart)
+        .with_retry_policy(self.retry)
+        .with_timeout_config(self.timeouts)
+    }
+
+    /// HeadBucket responses have no body, errors are
+    /// told apart by their status code
+    async fn head_bucket(&self, bucket_name: &str) -> BucketResult<()> {
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let head_bucket_req = HeadBucketRequest {
+                    bucket: bucket_name.to_string(),
+                    ..Default::default()
+                };
+                self.s3
+                    .head_bucket(head_bucket_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
+        match resp {
+            Ok(_) => Ok(()),
+            Err(details) => match details.kind() {
+                ErrorKind::NotFound => Err(BucketError::NotFound),
+                ErrorKind::PermissionDenied => {
+                    Err(BucketError::PermissionDenied(details))
+                }
+                _ => Err(BucketError::OpenError(details)),
+            },
         }
     }
 }
 
 impl AwsBucket {
-    pub async fn exists(location: &str, bucket: &str) -> bool {
-        let mut buckets = AwsBuckets::new(location);
+    pub async fn exists(location: &str, bucket: &str) -> BucketResult<bool> {
+        let mut buckets = AwsBuckets::new(location)?;
         buckets.exists(bucket).await
     }
 }
@@ -200,175 +1282,445 @@ impl Bucket<AwsBlob> for AwsBucket {
         &self,
         marker: Option<String>,
     ) -> BucketResult<(Vec<AwsBlob>, Option<String>)> {
-        let list_blob_req = ListObjectsRequest {
-            bucket: self.name.clone(),
+        let options = ListOptions {
             marker,
             ..Default::default()
         };
-        let resp = self.s3.list_objects(list_blob_req).await;
+        let list = self.list_blobs_with_options(options).await?;
+        Ok((list.blobs, list.marker))
+    }
+
+    /// start_after and the page marker are both S3 markers,
+    /// the furthest of the two is used
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<AwsBlob>> {
+        let marker =
+            std::cmp::max(options.start_after.clone(), options.marker.clone());
+        let (options, marker) = (&options, &marker);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let list_blob_req = ListObjectsRequest {
+                    bucket: self.name.clone(),
+                    prefix: options.prefix.clone(),
+                    delimiter: options.delimiter.clone(),
+                    marker: marker.clone(),
+                    max_keys: options.max_results.map(|max| max as i64),
+                    ..Default::default()
+                };
+                self.s3
+                    .list_objects(list_blob_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
         match resp {
             Ok(k) => {
-                let contents = k.contents.unwrap();
-                let mut ret: Vec<AwsBlob> = Vec::new();
-                for obj in contents.iter() {
-                    ret.push(AwsBlob::new(
-                        obj.key.clone(),
-                        obj.e_tag.clone(),
+                let mut blobs: Vec<AwsBlob> = Vec::new();
+                for obj in k.contents.unwrap_or_default() {
+                    let mut blob = AwsBlob::new(
+                        obj.key,
+                        obj.e_tag,
                         obj.size,
                         None,
                         None,
                         None,
                         self.name.clone(),
-                    ))
+                    );
+                    blob.properties.last_modified =
+                        obj.last_modified.as_deref().and_then(parse_time);
+                    blob.properties.storage_class = obj.storage_class;
+                    blob.bucket = Some(self.clone());
+                    blob.retry = self.retry;
+                    blob.timeouts = self.timeouts;
+                    blobs.push(blob)
                 }
-                Ok((ret, k.next_marker))
-            }
-            Err(e) => {
-                Err(BucketError::ListError(String::from(format!("{}", e))))
+                let prefixes: Vec<String> = k
+                    .common_prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|common| common.prefix)
+                    .collect();
+                // S3 only returns a next marker when listing with a
+                // delimiter, otherwise the last key is the marker
+                let marker = match k.is_truncated {
+                    Some(true) => k.next_marker.or_else(|| {
+                        let last_blob = blobs
+                            .last()
+                            .map(|blob| blob.properties.key.clone());
+                        std::cmp::max(last_blob, prefixes.last().cloned())
+                    }),
+                    _ => None,
+                };
+                Ok(BlobList {
+                    blobs,
+                    prefixes,
+                    marker,
+                })
             }
+            Err(e) => Err(BucketError::ListError(e)),
         }
     }
 
+    /// A content type replaces the metadata of the copy, the other
+    /// headers and metadata of the source are then looked up so the
+    /// copy keeps them
     async fn copy_blob(
         &self,
         blob_path: &str,
         blob_destination_path: &str,
         content_type: Option<String>,
     ) -> BlobResult<AwsBlob> {
-        let copy_source = format!("{}/{}", self.name.clone(), blob_path);
-        let re = Regex::new(r"(?P<bucket>.*?)/(?P<blob_path>.*)").unwrap();
-        if let Some(captures) = re.captures(blob_destination_path) {
-            let bucket = captures.name("bucket").unwrap().as_str().to_owned();
-            let key = captures.name("blob_path").unwrap().as_str().to_owned();
-            let copy_blob_req = CopyObjectRequest {
-                bucket: bucket.clone(),
-                key: key.clone(),
-                copy_source,
-                ..Default::default()
-            };
-            let resp = self.s3.copy_object(copy_blob_req).await;
-            match resp {
-                Ok(_) => Ok(AwsBlob::new(
-                    Some(key),
-                    None,
-                    None,
-                    None,
-                    content_type,
-                    None,
-                    bucket,
-                )),
-                Err(e) => {
-                    Err(BlobError::CopyError(String::from(format!("{}", e))))
+        let copy_source = copy_source(&self.name, blob_path);
+        let copy_source = &copy_source;
+        let (bucket, key) = split_destination(blob_destination_path)?;
+        let source = match content_type {
+            Some(_) => Some(self.stat_blob(blob_path).await?.properties),
+            None => None,
+        };
+        let (source, content_type) = (&source, &content_type);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let mut copy_blob_req = CopyObjectRequest {
+                    bucket: bucket.to_owned(),
+                    key: key.to_owned(),
+                    copy_source: copy_source.clone(),
+                    ..Default::default()
+                };
+                if let Some(source) = source {
+                    let source = source.clone();
+                    copy_blob_req = CopyObjectRequest {
+                        metadata_directive: Some("REPLACE".to_owned()),
+                        content_type: content_type.clone(),
+                        cache_control: source.cache_control,
+                        content_encoding: source.content_encoding,
+                        content_disposition: source.content_disposition,
+                        content_language: source.content_language,
+                        storage_class: source.storage_class,
+                        metadata: user_metadata(source.metadata),
+                        ..copy_blob_req
+                    };
                 }
+                self.s3
+                    .copy_object(copy_blob_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
+        match resp {
+            Ok(_) => {
+                let destination = AwsBucket {
+                    name: bucket.to_owned(),
+                    ..self.clone()
+                };
+                destination.stat_blob(key).await
             }
-        } else {
-            return Err(BlobError::CopyError(String::from(
-                r"Format blob_destination_path as {bucket}/{blob_path}",
-            )));
+            Err(e) => Err(BlobError::CopyError(e)),
         }
     }
 
-    async fn write_blob(
+    async fn write_blob_with_options(
         &self,
         blob_path: &str,
         content: Option<Bytes>,
-        content_type: Option<String>,
+        options: &WriteOptions,
     ) -> BlobResult<AwsBlob> {
-        let put_blob_req = PutObjectRequest {
-            bucket: self.name.to_owned(),
-            key: blob_path.to_string(),
-            content_type,
-            body: Some(content.unwrap().to_vec().into()),
-            ..Default::default()
-        };
-        let resp = self.s3.put_object(put_blob_req).await;
+        let content = content.unwrap_or_default();
+        if content.len() > self.multipart.threshold {
+            let content = futures::stream::once(async { Ok(content) });
+            return self
+                .write_blob_multipart_with_options(
+                    blob_path,
+                    Box::pin(content),
+                    options,
+                )
+                .await;
+        }
+        let content = &content;
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let options = options.clone();
+                let put_blob_req = PutObjectRequest {
+                    bucket: self.name.to_owned(),
+                    key: blob_path.to_string(),
+                    content_type: options.content_type,
+                    cache_control: options.cache_control,
+                    content_encoding: options.content_encoding,
+                    content_disposition: options.content_disposition,
+                    content_language: options.content_language,
+                    storage_class: options.storage_class,
+                    metadata: user_metadata(options.metadata),
+                    body: Some(content.to_vec().into()),
+                    ..Default::default()
+                };
+                self.s3
+                    .put_object(put_blob_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
         match resp {
-            Ok(k) => Ok(AwsBlob::new(
-                Some(blob_path.to_string()),
+            Ok(k) => Ok(self.written(
+                blob_path,
                 k.e_tag,
-                None,
-                None,
-                None,
-                None,
-                self.name.to_owned(),
+                content.len() as u64,
+                options.clone(),
             )),
-            Err(e) => {
-                Err(BlobError::WriteError(String::from(format!("{}", e))))
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    /// Content up to the multipart threshold is uploaded in a single
+    /// request, anything larger through write_blob_multipart
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_path: &str,
+        mut content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<AwsBlob> {
+        let mut buffer = BytesMut::new();
+        while buffer.len() <= self.multipart.threshold {
+            match content.next().await {
+                Some(chunk) => buffer.extend_from_slice(&chunk?),
+                None => {
+                    return self
+                        .write_blob_with_options(
+                            blob_path,
+                            Some(buffer.freeze()),
+                            options,
+                        )
+                        .await
+                }
+            }
+        }
+        let buffered = buffer.freeze();
+        let content =
+            futures::stream::once(async { Ok(buffered) }).chain(content);
+        self.write_blob_multipart_with_options(
+            blob_path,
+            Box::pin(content),
+            options,
+        )
+        .await
+    }
+
+    /// Copies the object onto itself replacing its metadata, the
+    /// current storage class is looked up first as a copy would
+    /// otherwise move the object to STANDARD
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<AwsBlob> {
+        let current = self.stat_blob(blob_path).await?;
+        let storage_class = &current.properties.storage_class.clone();
+        let copy_source = copy_source(&self.name, blob_path);
+        let copy_source = &copy_source;
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let options = options.clone();
+                let copy_blob_req = CopyObjectRequest {
+                    bucket: self.name.to_owned(),
+                    key: blob_path.to_owned(),
+                    copy_source: copy_source.clone(),
+                    metadata_directive: Some("REPLACE".to_owned()),
+                    content_type: options.content_type,
+                    cache_control: options.cache_control,
+                    content_encoding: options.content_encoding,
+                    content_disposition: options.content_disposition,
+                    content_language: options.content_language,
+                    storage_class: storage_class.clone(),
+                    metadata: user_metadata(options.metadata),
+                    ..Default::default()
+                };
+                self.s3
+                    .copy_object(copy_blob_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
+        match resp {
+            Ok(k) => {
+                let copied = k.copy_object_result.unwrap_or_default();
+                let options = WriteOptions {
+                    storage_class: current.properties.storage_class,
+                    ..options.clone()
+                };
+                let size = current.properties.size.unwrap_or_default();
+                let mut blob =
+                    self.written(blob_path, copied.e_tag, size, options);
+                blob.properties.last_modified =
+                    copied.last_modified.as_deref().and_then(parse_time);
+                Ok(blob)
             }
+            Err(e) => Err(BlobError::WriteError(e)),
         }
     }
 
     async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
-        let delete_blob_req = DeleteObjectRequest {
-            bucket: self.name.clone(),
-            key: blob_path.to_string(),
-            ..Default::default()
-        };
-        let resp = self.s3.delete_object(delete_blob_req).await;
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let delete_blob_req = DeleteObjectRequest {
+                    bucket: self.name.clone(),
+                    key: blob_path.to_string(),
+                    ..Default::default()
+                };
+                self.s3
+                    .delete_object(delete_blob_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
         match resp {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::DeletionError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::DeletionError(e)),
         }
     }
 
+    async fn signed_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        self.signed_url_at(blob_path, method, expires_in, Utc::now())
+            .await
+    }
+
+    async fn post_policy(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        self.post_policy_at(conditions, expires_in, Utc::now())
+            .await
+    }
+
     async fn get_blob(
         &self,
         blob_path: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<AwsBlob> {
-        let get_blob_req = GetObjectRequest {
-            bucket: self.name.clone(),
-            key: blob_path.to_string(),
-            range: content_range,
-            ..Default::default()
-        };
-        let resp = self.s3.get_object(get_blob_req).await;
+        let range = range.map(|range| range.to_string());
+        let range = &range;
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let get_blob_req = GetObjectRequest {
+                    bucket: self.name.clone(),
+                    key: blob_path.to_string(),
+                    range: range.clone(),
+                    ..Default::default()
+                };
+                self.s3
+                    .get_object(get_blob_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
         match resp {
             Ok(k) => {
-                let blob = AwsBlob::new(
+                let mut blob = AwsBlob::new(
                     Some(blob_path.to_string()),
                     k.e_tag.clone(),
-                    k.content_length.clone(),
+                    k.content_length,
                     k.body,
                     k.content_type,
                     k.content_range,
                     self.name.clone(),
                 );
+                let properties = &mut blob.properties;
+                properties.content_encoding = k.content_encoding;
+                properties.cache_control = k.cache_control;
+                properties.content_disposition = k.content_disposition;
+                properties.content_language = k.content_language;
+                properties.last_modified =
+                    k.last_modified.as_deref().and_then(parse_time);
+                properties.storage_class = k.storage_class;
+                properties.metadata = k.metadata.unwrap_or_default();
+                blob.bucket = Some(self.clone());
+                blob.retry = self.retry;
+                blob.timeouts = self.timeouts;
+                Ok(blob)
+            }
+            Err(e) => Err(BlobError::GetError(e)),
+        }
+    }
+
+    /// Issues a HeadObject, the body is only downloaded on read
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<AwsBlob> {
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let head_req = HeadObjectRequest {
+                    bucket: self.name.clone(),
+                    key: blob_path.to_string(),
+                    ..Default::default()
+                };
+                self.s3
+                    .head_object(head_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
+        match resp {
+            Ok(k) => {
+                let mut blob = AwsBlob::new(
+                    Some(blob_path.to_string()),
+                    k.e_tag,
+                    k.content_length,
+                    None,
+                    k.content_type,
+                    None,
+                    self.name.clone(),
+                );
+                let properties = &mut blob.properties;
+                properties.content_encoding = k.content_encoding;
+                properties.cache_control = k.cache_control;
+                properties.content_disposition = k.content_disposition;
+                properties.content_language = k.content_language;
+                properties.last_modified =
+                    k.last_modified.as_deref().and_then(parse_time);
+                properties.storage_class = k.storage_class;
+                properties.metadata = k.metadata.unwrap_or_default();
+                blob.bucket = Some(self.clone());
+                blob.retry = self.retry;
+                blob.timeouts = self.timeouts;
                 Ok(blob)
             }
-            Err(e) => Err(BlobError::GetError(String::from(format!("{}", e)))),
+            Err(e) => Err(BlobError::GetError(e)),
         }
     }
 }
 
 #[async_trait]
 impl Buckets<AwsBucket, AwsBlob> for AwsBuckets {
-    async fn list(&mut self) -> Vec<AwsBucket> {
-        let resp = self.s3.list_buckets().await.unwrap();
+    async fn list(&mut self) -> BucketResult<Vec<AwsBucket>> {
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async {
+                self.s3.list_buckets().await.map_err(ErrorDetails::from)
+            })
+            .await
+            .map_err(BucketError::ListError)?;
         let mut buckets: Vec<AwsBucket> = Vec::new();
-        for bucket in resp.buckets.unwrap().iter() {
-            if bucket.name.is_some() {
-                let bucket_found = AwsBucket::new(
-                    String::from(bucket.name.clone().unwrap()),
-                    Some(self.s3.clone()),
-                );
-                buckets.push(bucket_found);
+        for bucket in resp.buckets.unwrap_or_default().iter() {
+            if let Some(ref name) = bucket.name {
+                buckets.push(self.bucket(name));
             }
         }
-        buckets
+        Ok(buckets)
     }
 
     async fn open(&mut self, bucket_name: &str) -> BucketResult<AwsBucket> {
-        if self.exists(&bucket_name).await {
-            Ok(AwsBucket {
-                name: bucket_name.to_string(),
-                s3: self.s3.clone(),
-            })
-        } else {
-            Err(BucketError::NotFound)
-        }
+        self.head_bucket(bucket_name).await?;
+        Ok(self.bucket(bucket_name))
     }
 
     async fn create(
@@ -383,44 +1735,50 @@ impl Buckets<AwsBucket, AwsBlob> for AwsBuckets {
             }),
             ..Default::default()
         };
-        let resp = self.s3.create_bucket(create_bucket_req).await;
+        let resp = self
+            .timeouts
+            .send_once(async {
+                self.s3
+                    .create_bucket(create_bucket_req)
+                    .await
+                    .map_err(ErrorDetails::from)
+            })
+            .await;
         match resp {
-            Ok(_) => Ok(AwsBucket {
-                name: bucket_name.to_string(),
-                s3: self.s3.clone(),
-            }),
-            Err(e) => {
-                Err(BucketError::CreationError(String::from(format!("{}", e))))
-            }
+            Ok(_) => Ok(self.bucket(bucket_name)),
+            Err(e) => Err(BucketError::CreationError(e)),
         }
     }
 
     async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool> {
-        if self.exists(bucket_name).await {
-            let delete_bucket_req = DeleteBucketRequest {
-                bucket: bucket_name.to_string(),
-                ..Default::default()
-            };
-
-            let resp = self.s3.delete_bucket(delete_bucket_req).await;
+        if self.exists(bucket_name).await? {
+            let s3 = &self.s3;
+            let resp = self
+                .timeouts
+                .send(&self.retry, || async move {
+                    let delete_bucket_req = DeleteBucketRequest {
+                        bucket: bucket_name.to_string(),
+                        ..Default::default()
+                    };
+                    s3.delete_bucket(delete_bucket_req)
+                        .await
+                        .map_err(ErrorDetails::from)
+                })
+                .await;
             match resp {
                 Ok(_) => Ok(true),
-                Err(e) => Err(BucketError::DeletionError(String::from(
-                    format!("{}", e),
-                ))),
+                Err(e) => Err(BucketError::DeletionError(e)),
             }
         } else {
             Err(BucketError::NotFound)
         }
     }
 
-    async fn exists(&mut self, bucket_name: &str) -> bool {
-        let bucket_list = self.list().await;
-        for bucket in bucket_list {
-            if bucket.name == bucket_name {
-                return true;
-            }
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        match self.head_bucket(bucket_name).await {
+            Ok(_) => Ok(true),
+            Err(BucketError::NotFound) => Ok(false),
+            Err(e) => Err(e),
         }
-        false
     }
 }
@@ -1,24 +1,69 @@
-use crate::types::blob::Blob;
-use crate::types::bucket::{Bucket, Buckets};
-use crate::types::errors::{BlobError, BlobResult, BucketError, BucketResult};
+use crate::providers::chunked;
+use crate::providers::split_destination;
+use crate::types::blob::{
+    Blob, BlobProperties, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
+use crate::types::errors::{
+    BlobError, BlobResult, BucketError, BucketResult, ErrorDetails, ErrorKind,
+    ErrorSource,
+};
+use crate::types::retry::RetryPolicy;
+use crate::types::timeout::TimeoutConfig;
 use async_trait::async_trait;
 use azure_core::prelude::*;
-use azure_storage_blobs::prelude::*;
+use azure_storage::core::clients::{ServiceType, StorageCredentials};
 use azure_storage::core::prelude::*;
-use bytes::Bytes;
-use futures::stream::StreamExt;
-use regex::Regex;
+use azure_storage::core::shared_access_signature::service_sas::BlobSasPermissions;
+use azure_storage_blobs::prelude::*;
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::stream::{StreamExt, TryStreamExt};
+use reqwest::StatusCode;
+use std::convert::TryFrom;
 use std::time::Duration;
+use url::Url;
+
+/// Controls when and how blobs are uploaded in blocks
+#[derive(Debug, Clone, Copy)]
+pub struct BlockUploadConfig {
+    /// Size of each block, a blob has at most 50000 blocks
+    /// so this also bounds the size of blobs, larger ones fail
+    /// with InvalidArgument before their 50001st block is sent
+    pub block_size: usize,
+    /// Number of blocks uploaded at the same time
+    pub concurrency: usize,
+    /// Blobs larger than this are uploaded in blocks
+    pub threshold: usize,
+}
+
+impl Default for BlockUploadConfig {
+    fn default() -> Self {
+        BlockUploadConfig {
+            block_size: 4 * 1024 * 1024,
+            concurrency: 4,
+            threshold: 16 * 1024 * 1024,
+        }
+    }
+}
+/// Size of the ranges read_stream downloads the blob in
+const READ_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+/// Most blocks a blob can be committed with
+const MAX_BLOCKS: usize = 50_000;
 
 #[derive(Debug)]
 pub struct AzureBlob {
-    key: String,
-    e_tag: azure_core::Etag,
+    properties: BlobProperties,
     body: Option<Vec<u8>>,
-    content_type: String,
-    content_length: u64,
-    container: String,
+    content_range: Option<String>,
     storage_account: String,
+    /// Container the blob comes from, opened from the environment
+    /// when the blob was built through new
+    container: Option<AzureBucket>,
+    retry: RetryPolicy,
+    timeouts: TimeoutConfig,
 }
 
 impl AzureBlob {
@@ -32,105 +77,579 @@ impl AzureBlob {
         storage_account: String,
     ) -> Self {
         AzureBlob {
-            key,
-            e_tag,
+            properties: BlobProperties {
+                e_tag: Some(e_tag.to_string()),
+                size: Some(content_length),
+                content_type: Some(content_type),
+                ..BlobProperties::new(key, container)
+            },
             body,
-            content_type,
-            content_length,
-            container,
+            content_range: None,
             storage_account,
+            container: None,
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
         }
     }
+
     pub async fn get(
         storage_account: &str,
         container: &str,
         blob_name: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<Self> {
-        let mut buckets = AzureBuckets::new(storage_account.to_owned());
+        let mut buckets = AzureBuckets::new(storage_account.to_owned())
+            .map_err(|e| BlobError::GetError(e.into()))?;
         let bucket = buckets.open(container).await;
         match bucket {
-            Ok(b) => b.get_blob(blob_name, content_range).await,
-            Err(e) => Err(BlobError::GetError(e.to_string())),
+            Ok(b) => b.get_blob(blob_name, range).await,
+            Err(e) => Err(BlobError::GetError(e.into())),
+        }
+    }
+}
+
+impl AzureBlob {
+    /// Container the blob belongs to, with the client, retry policy
+    /// and timeouts of the one it comes from
+    async fn bucket(&self) -> BucketResult<AzureBucket> {
+        if let Some(ref container) = self.container {
+            return Ok(container.clone());
         }
+        AzureBuckets::new(self.storage_account.to_owned())?
+            .with_retry_policy(self.retry)
+            .with_timeout_config(self.timeouts)
+            .open(&self.properties.bucket)
+            .await
     }
 }
 
 #[async_trait]
 impl Blob for AzureBlob {
+    fn properties(&self) -> &BlobProperties {
+        &self.properties
+    }
+
+    fn content_range(&self) -> Option<&str> {
+        self.content_range.as_deref()
+    }
+
     async fn copy(
         &self,
         blob_destination_path: &str,
         content_type: Option<String>,
     ) -> BlobResult<bool> {
-        let mut buckets = AzureBuckets::new(self.storage_account.to_owned());
-        let bucket = buckets.open(&self.container).await.unwrap();
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::CopyError(e.into()))?;
         let copied = bucket
-            .copy_blob(&self.key, blob_destination_path, content_type)
+            .copy_blob(
+                &self.properties.key,
+                blob_destination_path,
+                content_type,
+            )
             .await;
         match copied {
             Ok(_) => Ok(true),
-            Err(e) => Err(BlobError::CopyError(String::from(format!("{}", e)))),
+            Err(e) => Err(BlobError::CopyError(e.into())),
         }
     }
 
-    async fn write(
+    async fn write_with_options(
         &self,
         content: Option<Bytes>,
-        content_type: Option<String>,
+        options: &WriteOptions,
     ) -> BlobResult<bool> {
-        let mut buckets = AzureBuckets::new(self.storage_account.to_owned());
-        let bucket = buckets.open(&self.container).await.unwrap();
-        let write = bucket.write_blob(&self.key, content, content_type).await;
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))?;
+        let write = bucket
+            .write_blob_with_options(&self.properties.key, content, options)
+            .await;
         match write {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::WriteError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::WriteError(e.into())),
         }
     }
+
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()> {
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))?;
+        let updated = bucket
+            .update_blob_metadata(&self.properties.key, options)
+            .await?;
+        self.properties = updated.properties;
+        Ok(())
+    }
+
+    /// Returns the content fetched by get_blob, blobs obtained
+    /// any other way download the blob
     async fn read(&mut self) -> BlobResult<Bytes> {
-        let buckets = AzureBuckets::new(self.storage_account.to_owned());
-        let blob_client = buckets
+        if let Some(ref body) = self.body {
+            return Ok(Bytes::from(body.clone()));
+        }
+        let blob_client = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?
             .client
-            .as_container_client(&self.container)
-            .as_blob_client(&self.key);
+            .as_blob_client(&self.properties.key);
 
-        let mut complete_response = Vec::new();
+        let blob_client = &blob_client;
+        // a failed download starts over from the first chunk
+        let complete_response = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let mut complete_response = Vec::new();
+                let mut stream = Box::pin(blob_client.get().stream(1024 * 8));
+                while let Some(value) = stream.next().await {
+                    let data = value.map_err(azure_error)?;
+                    complete_response.extend(&data.data as &[u8]);
+                }
+                Ok(complete_response)
+            })
+            .await
+            .map_err(BlobError::ReadError)?;
+        Ok(Bytes::from(complete_response))
+    }
 
-        let mut stream = Box::pin(blob_client.get().stream(1024 * 8));
-        while let Some(value) = stream.next().await {
-            let data = value.unwrap().data;
-            complete_response.extend(&data as &[u8]);
+    /// Downloads the blob one range at a time, a range is only
+    /// requested once the previous chunk has been consumed
+    async fn read_stream(&mut self) -> BlobResult<BlobStream> {
+        if let Some(body) = self.body.take() {
+            let body = Bytes::from(body);
+            return Ok(Box::pin(futures::stream::once(async { Ok(body) })));
         }
-        Ok(Bytes::from(complete_response))
+        let blob_client = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?
+            .client
+            .as_blob_client(&self.properties.key);
+        let size = self.properties.size.unwrap_or_default();
+        let (retry, timeouts) = (self.retry, self.timeouts);
+        let stream = futures::stream::unfold(0, move |start| {
+            let blob_client = blob_client.clone();
+            async move {
+                if start >= size {
+                    return None;
+                }
+                let end = std::cmp::min(start + READ_CHUNK_SIZE, size);
+                let blob_client = &blob_client;
+                let resp = timeouts
+                    .send(&retry, || async move {
+                        blob_client
+                            .get()
+                            .range(Range::new(start, end))
+                            .execute()
+                            .await
+                            .map_err(azure_error)
+                    })
+                    .await;
+                match resp {
+                    Ok(r) => Some((Ok(r.data), end)),
+                    Err(e) => Some((Err(BlobError::ReadError(e)), size)),
+                }
+            }
+        });
+        Ok(Box::pin(stream))
     }
 
     async fn delete(&self) -> BlobResult<bool> {
-        let mut buckets = AzureBuckets::new(self.storage_account.to_owned());
-        let bucket = buckets.open(&self.container).await.unwrap();
-        let del = bucket.delete_blob(&self.key).await;
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::DeletionError(e.into()))?;
+        let del = bucket.delete_blob(&self.properties.key).await;
         match del {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::DeletionError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::DeletionError(e.into())),
         }
     }
+
+    async fn signed_url(
+        &self,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        self.bucket()
+            .await
+            .map_err(|e| BlobError::SignError(e.into()))?
+            .signed_url(&self.properties.key, method, expires_in)
+            .await
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct AzureBucket {
     pub name: String,
     pub client: std::sync::Arc<ContainerClient>,
+    pub account_client: std::sync::Arc<StorageAccountClient>,
     pub storage_account: String,
+    pub block_upload: BlockUploadConfig,
+    pub retry: RetryPolicy,
+    pub timeouts: TimeoutConfig,
+}
+
+/// The clients are left out as they print the credentials
+impl std::fmt::Debug for AzureBucket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AzureBucket")
+            .field("name", &self.name)
+            .field("storage_account", &self.storage_account)
+            .field("block_upload", &self.block_upload)
+            .field("retry", &self.retry)
+            .field("timeouts", &self.timeouts)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AzureBucket {
-    pub async fn exists(storage_account: &str, bucket: &str) -> bool {
-        let mut buckets = AzureBuckets::new(storage_account.to_owned());
+    /// Service SAS url granting method on blob_path until expiry,
+    /// signed with the account key
+    pub(crate) fn sas_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expiry: DateTime<Utc>,
+    ) -> BlobResult<String> {
+        let unsupported = |e: &dyn std::fmt::Display| {
+            BlobError::SignError(ErrorDetails::new(
+                ErrorKind::Unsupported,
+                e.to_string(),
+            ))
+        };
+        let permissions = match method {
+            HttpMethod::Get => BlobSasPermissions {
+                read: true,
+                ..Default::default()
+            },
+            HttpMethod::Put => BlobSasPermissions {
+                create: true,
+                write: true,
+                ..Default::default()
+            },
+        };
+        // the key is decoded without checking when signing
+        if let StorageCredentials::Key(_, key) =
+            self.account_client.storage_credentials()
+        {
+            base64::decode(key).map_err(|e| {
+                BlobError::SignError(ErrorDetails::from_source(
+                    ErrorKind::InvalidArgument,
+                    e,
+                ))
+            })?;
+        }
+        let blob = self.client.as_blob_client(blob_path);
+        // only clients holding the account key can sign
+        let sas = blob
+            .shared_access_signature()
+            .map_err(|e| unsupported(&e))?
+            .with_permissions(permissions)
+            .with_expiry(expiry)
+            .finalize();
+        let url = blob.generate_signed_blob_url(&sas).map_err(|e| {
+            BlobError::SignError(ErrorDetails::from_source(
+                ErrorKind::InvalidArgument,
+                e,
+            ))
+        })?;
+        Ok(url.into())
+    }
+
+    pub async fn exists(
+        storage_account: &str,
+        bucket: &str,
+    ) -> BucketResult<bool> {
+        let mut buckets = AzureBuckets::new(storage_account.to_owned())?;
         buckets.exists(bucket).await
     }
+
+    /// Set how blobs are uploaded in blocks
+    pub fn with_block_upload_config(
+        mut self,
+        config: BlockUploadConfig,
+    ) -> Self {
+        self.block_upload = config;
+        self
+    }
+
+    /// Set how failed requests of this container and its blobs
+    /// are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set how long requests of this container and its blobs may take,
+    /// TimeoutConfig::scope gives a single call its own
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        self.timeouts = config;
+        self
+    }
+
+    /// Another container of the account, with the settings of this one
+    fn container(&self, container: &str) -> AzureBucket {
+        AzureBucket {
+            name: container.to_owned(),
+            client: self.account_client.as_container_client(container),
+            ..self.clone()
+        }
+    }
+
+    /// Upload content block by block whatever its size, then commit
+    /// the block list. Blocks are uploaded concurrently and each is
+    /// retried on failure. Blocks of a failed upload are never
+    /// committed and are discarded by Azure after a week
+    pub async fn write_blob_blocks(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        content_type: Option<String>,
+    ) -> BlobResult<AzureBlob> {
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        self.write_blob_blocks_with_options(blob_name, content, &options)
+            .await
+    }
+
+    /// Upload content block by block with the properties and
+    /// metadata of options, see write_blob_blocks
+    pub async fn write_blob_blocks_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<AzureBlob> {
+        let headers = write_headers(options).map_err(BlobError::WriteError)?;
+        let block_size = std::cmp::max(self.block_upload.block_size, 1);
+        let concurrency = std::cmp::max(self.block_upload.concurrency, 1);
+        let blocks: Vec<BlobBlockType> = chunked(content, block_size)
+            .enumerate()
+            .map(|(i, block)| async move {
+                if i >= MAX_BLOCKS {
+                    return Err(BlobError::WriteError(ErrorDetails::new(
+                        ErrorKind::InvalidArgument,
+                        format!(
+                            "Content needs more than {} blocks of {} bytes",
+                            MAX_BLOCKS, block_size
+                        ),
+                    )));
+                }
+                // block ids of a blob must all have the same length
+                let block_id = BlockId::new(format!("{:08}", i));
+                self.put_block(blob_name, &block_id, block?).await?;
+                Ok(BlobBlockType::new_latest(block_id))
+            })
+            .buffered(concurrency)
+            .try_collect()
+            .await?;
+        let block_list = Bytes::from(BlockList { blocks }.to_xml());
+        let (block_list, headers) = (&block_list, &headers);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let body = Some(block_list.clone());
+                self.send_raw(
+                    blob_name,
+                    "comp=blocklist",
+                    headers,
+                    body,
+                    StatusCode::CREATED,
+                )
+                .await
+            })
+            .await;
+        match resp {
+            Ok(_) => self.blob_properties(blob_name).await,
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    async fn put_block(
+        &self,
+        blob_name: &str,
+        block_id: &BlockId,
+        block: Bytes,
+    ) -> BlobResult<()> {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("comp", "block")
+            .append_pair("blockid", &base64::encode(block_id.as_ref()))
+            .finish();
+        let (query, block) = (&query, &block);
+        self.timeouts
+            .send(&self.retry, || async move {
+                let body = Some(block.clone());
+                self.send_raw(blob_name, query, &[], body, StatusCode::CREATED)
+                    .await
+            })
+            .await
+            .map_err(BlobError::WriteError)
+    }
+
+    /// Replace the content type of a blob, the other properties are
+    /// sent again as setting properties clears those it is not given
+    async fn set_content_type(
+        &self,
+        blob_path: &str,
+        content_type: String,
+    ) -> Result<(), ErrorDetails> {
+        let current = self.fetch_properties(blob_path).await?;
+        let options = WriteOptions {
+            content_type: Some(content_type),
+            cache_control: current.properties.cache_control.clone(),
+            content_encoding: current.properties.content_encoding.clone(),
+            content_disposition: current.properties.content_disposition.clone(),
+            content_language: current.properties.content_language.clone(),
+            ..Default::default()
+        };
+        let mut headers = property_headers(&options)?;
+        headers.extend(content_md5_header(&current));
+        let headers = &headers;
+        self.timeouts
+            .send(&self.retry, || async move {
+                self.send_raw(
+                    blob_path,
+                    "comp=properties",
+                    headers,
+                    None,
+                    StatusCode::OK,
+                )
+                .await
+            })
+            .await
+    }
+
+    /// Send a PUT to blob_path signed by the account client, for
+    /// requests whose headers the SDK's builders cannot all set.
+    /// Any status but expected is an error
+    async fn send_raw(
+        &self,
+        blob_path: &str,
+        query: &str,
+        headers: &[(String, String)],
+        body: Option<Bytes>,
+        expected: StatusCode,
+    ) -> Result<(), ErrorDetails> {
+        let mut url = self
+            .account_client
+            .as_storage_client()
+            .blob_url_with_segments(
+                std::iter::once(self.name.as_str()).chain(blob_path.split('/')),
+            )
+            .map_err(|e| {
+                ErrorDetails::from_source(ErrorKind::InvalidArgument, e)
+            })?;
+        if !query.is_empty() {
+            url.set_query(Some(query));
+        }
+        let (request, _) = self
+            .account_client
+            .prepare_request(
+                url.as_str(),
+                &reqwest::Method::PUT,
+                &|mut request| {
+                    for (name, value) in headers {
+                        request = request.header(name.as_str(), value.as_str());
+                    }
+                    request
+                },
+                ServiceType::Blob,
+                body,
+            )
+            .map_err(|e| {
+                ErrorDetails::from_source(ErrorKind::InvalidArgument, e)
+            })?;
+        let resp = self
+            .account_client
+            .http_client()
+            .execute_request(request)
+            .await
+            .map_err(|e| azure_error(Box::new(e)))?;
+        if resp.status() == expected {
+            return Ok(());
+        }
+        let error = azure_core::HttpError::StatusCode {
+            status: resp.status(),
+            body: String::from_utf8_lossy(resp.body()).into_owned(),
+        };
+        Err(azure_error(Box::new(error)))
+    }
+
+    /// Blob fetched through its properties so its content
+    /// is not downloaded
+    async fn blob_properties(&self, blob_name: &str) -> BlobResult<AzureBlob> {
+        match self.fetch_properties(blob_name).await {
+            Ok(blob) => Ok(self.blob(blob, None)),
+            Err(details) if details.kind() == ErrorKind::NotFound => {
+                Err(BlobError::NotFound)
+            }
+            Err(details) => Err(BlobError::GetError(details)),
+        }
+    }
+
+    async fn fetch_properties(
+        &self,
+        blob_name: &str,
+    ) -> Result<azure_storage_blobs::blob::Blob, ErrorDetails> {
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                self.client
+                    .as_blob_client(blob_name)
+                    .get_properties()
+                    .execute()
+                    .await
+                    .map_err(azure_error)
+            })
+            .await;
+        resp.map(|k| k.blob)
+    }
+
+    fn blob(
+        &self,
+        blob: azure_storage_blobs::blob::Blob,
+        body: Option<Vec<u8>>,
+    ) -> AzureBlob {
+        let content_length = match body {
+            Some(ref body) => body.len() as u64,
+            None => blob.properties.content_length,
+        };
+        let mut found = AzureBlob::new(
+            blob.name,
+            blob.properties.etag,
+            body,
+            blob.properties.content_type,
+            content_length,
+            self.name.clone(),
+            self.storage_account.clone(),
+        );
+        let properties = &mut found.properties;
+        properties.content_encoding = blob.properties.content_encoding;
+        properties.cache_control = blob.properties.cache_control;
+        properties.content_disposition = blob.properties.content_disposition;
+        properties.content_language = blob.properties.content_language;
+        properties.last_modified = Some(blob.properties.last_modified);
+        properties.created = Some(blob.properties.creation_time);
+        properties.storage_class = blob
+            .properties
+            .access_tier
+            .map(|tier| <&str>::from(tier).to_owned());
+        properties.metadata = blob.metadata.unwrap_or_default();
+        found.container = Some(self.clone());
+        found.retry = self.retry;
+        found.timeouts = self.timeouts;
+        found
+    }
 }
 
 #[async_trait]
@@ -138,239 +657,691 @@ impl Bucket<AzureBlob> for AzureBucket {
     async fn get_blob(
         &self,
         blob_path: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<AzureBlob> {
-        let resp = self.client.as_blob_client(blob_path).get().execute().await;
+        // without a range only the properties are fetched, the content
+        // is downloaded in chunks by read_stream or at once by read
+        let range = match range {
+            Some(range) => range,
+            None => {
+                return self
+                    .fetch_properties(blob_path)
+                    .await
+                    .map(|blob| self.blob(blob, None))
+                    .map_err(BlobError::GetError)
+            }
+        };
+        let blob_client = &self.client.as_blob_client(blob_path);
+        // x-ms-range takes no suffix ranges, their start is resolved
+        // from the size. Other ranges are sent as they are and the
+        // size is read from the content range of the response
+        let bounds = match range {
+            ByteRange::Bounded(start, end) => {
+                Range::new(start, end.saturating_add(1))
+            }
+            ByteRange::From(start) => Range::new(start, u64::MAX),
+            suffix @ ByteRange::Suffix(_) => {
+                let size = self
+                    .timeouts
+                    .send(&self.retry, || async move {
+                        blob_client
+                            .get_properties()
+                            .execute()
+                            .await
+                            .map_err(azure_error)
+                    })
+                    .await
+                    .map_err(BlobError::GetError)?
+                    .blob
+                    .properties
+                    .content_length;
+                let (start, end) = suffix.resolve(size)?;
+                Range::new(start, end + 1)
+            }
+        };
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                blob_client
+                    .get()
+                    .range(bounds)
+                    .execute()
+                    .await
+                    .map_err(azure_error)
+            })
+            .await;
         match resp {
-            Ok(k) => Ok(AzureBlob::new(
-                k.blob.name.to_owned(),
-                k.blob.properties.etag.to_owned(),
-                None,
-                k.blob.properties.content_type.to_owned(),
-                k.blob.properties.content_length,
-                self.name.clone(),
-                self.storage_account.clone(),
-            )),
-            Err(e) => Err(BlobError::GetError(String::from(format!("{}", e)))),
+            Ok(k) => {
+                let content_range = match k.content_range {
+                    Some(returned) => {
+                        Some(range.content_range(returned.total_length())?)
+                    }
+                    None => None,
+                };
+                let mut blob = self.blob(k.blob, Some(k.data.to_vec()));
+                blob.content_range = content_range;
+                Ok(blob)
+            }
+            Err(e) => Err(BlobError::GetError(e)),
         }
     }
 
+    /// Fetches the blob properties, the content is only
+    /// downloaded on read
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<AzureBlob> {
+        self.blob_properties(blob_path).await
+    }
+
     async fn copy_blob(
         &self,
         blob_path: &str,
         blob_destination_path: &str,
         content_type: Option<String>,
     ) -> BlobResult<AzureBlob> {
-        let re = Regex::new(r"(?P<bucket>.*?)/(?P<blob_path>.*)").unwrap();
-        if let Some(captures) = re.captures(blob_destination_path) {
-            let bucket = captures.name("bucket").unwrap().as_str().to_owned();
-            let key = captures.name("blob_path").unwrap().as_str().to_owned();
-            let absolute_path;
-            if bucket == self.name {
-                absolute_path = key;
-            } else {
-                absolute_path = format!("{}/{}", bucket, key).to_owned();
-            }
-            let buckets = AzureBuckets::new(self.storage_account.to_owned());
-            let source_url = format!(
-                "{}{}/{}",
-                buckets.account_client.blob_storage_url().as_str(),
-                self.name,
-                blob_path
-            );
-            let blob = self.client.as_blob_client(&absolute_path);
-
-            let response = blob
-                .copy_from_url(&source_url)
-                .is_synchronous(true)
-                .execute()
-                .await;
-            match response {
-                Ok(_) => {
-                    let blob =
-                        self.get_blob(absolute_path.as_str(), None).await;
-                    match blob {
-                        Ok(blob) => Ok(blob),
-                        Err(_) => Err(BlobError::NotFound),
-                    }
-                }
-                Err(e) => {
-                    Err(BlobError::GetError(String::from(format!("{}", e))))
-                }
-            }
+        let (bucket, key) = split_destination(blob_destination_path)?;
+        let destination = if bucket == self.name {
+            self.clone()
         } else {
-            return Err(BlobError::CopyError(String::from(
-                r"Format blob_destination_path as {bucket}/{blob_path}",
-            )));
+            self.container(bucket)
+        };
+        let source_url = self
+            .account_client
+            .as_storage_client()
+            .blob_url_with_segments(
+                std::iter::once(self.name.as_str()).chain(blob_path.split('/')),
+            )
+            .map_err(|e| {
+                BlobError::CopyError(ErrorDetails::from_source(
+                    ErrorKind::InvalidArgument,
+                    e,
+                ))
+            })?;
+        let blob = &destination.client.as_blob_client(key);
+        let source_url = source_url.as_str();
+        let response = self
+            .timeouts
+            .send(&self.retry, || async move {
+                blob.copy_from_url(source_url)
+                    .is_synchronous(true)
+                    .execute()
+                    .await
+                    .map_err(azure_error)
+            })
+            .await;
+        if let Err(e) = response {
+            return Err(BlobError::CopyError(e));
+        }
+        // a copy keeps the properties of its source
+        if let Some(content_type) = content_type {
+            destination
+                .set_content_type(key, content_type)
+                .await
+                .map_err(BlobError::CopyError)?;
         }
+        destination.blob_properties(key).await
     }
 
     async fn list_blobs(
         &self,
         marker: Option<String>,
     ) -> BucketResult<(Vec<AzureBlob>, Option<String>)> {
-        let next_marker = NextMarker::from_possibly_empty_string(marker);
-        let response;
-        match next_marker {
-            Some(marker) => {
-                response = self
-                    .client
-                    .list_blobs()
-                    .next_marker(marker)
-                    .execute()
-                    .await;
-            }
-            None => {
-                response = self.client.list_blobs().execute().await;
-            }
+        let options = ListOptions {
+            marker,
+            ..Default::default()
+        };
+        let list = self.list_blobs_with_options(options).await?;
+        Ok((list.blobs, list.marker))
+    }
+
+    /// Azure has no start offset for listings, blobs up to start_after
+    /// are filtered from each page instead
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<AzureBlob>> {
+        let mut request = self.client.list_blobs().include_metadata(true);
+        if let Some(prefix) = options.prefix {
+            request = request.prefix(prefix);
+        }
+        if let Some(delimiter) = options.delimiter.as_deref() {
+            request = request.delimiter(delimiter);
         }
+        if let Some(max_results) = options.max_results {
+            let max_results = MaxResults::try_from(max_results as u32)
+                .map_err(|e| {
+                    BucketError::ListError(ErrorDetails::from_source(
+                        ErrorKind::InvalidArgument,
+                        e,
+                    ))
+                })?;
+            request = request.max_results(max_results);
+        }
+        if let Some(marker) =
+            NextMarker::from_possibly_empty_string(options.marker)
+        {
+            request = request.next_marker(marker);
+        }
+        let request = &request;
+        let res = self
+            .timeouts
+            .send(&self.retry, || async move {
+                request.clone().execute().await.map_err(azure_error)
+            })
+            .await
+            .map_err(BucketError::ListError)?;
+        let start_after = options.start_after.unwrap_or_default();
+        let blobs: Vec<AzureBlob> = res
+            .blobs
+            .blobs
+            .iter()
+            .filter(|blob| blob.name > start_after)
+            .map(|blob| self.blob(blob.clone(), None))
+            .collect();
+        let prefixes: Vec<String> = res
+            .blobs
+            .blob_prefix
+            .unwrap_or_default()
+            .into_iter()
+            .map(|prefix| prefix.name)
+            .filter(|prefix| *prefix > start_after)
+            .collect();
+        let marker = res
+            .next_marker
+            .as_ref()
+            .map(|marker| marker.as_str().to_owned());
+        Ok(BlobList {
+            blobs,
+            prefixes,
+            marker,
+        })
+    }
 
-        let mut res = response.unwrap();
-        let mut blobs: Vec<AzureBlob> = Vec::new();
-        for blob in &mut res.blobs.blobs.iter() {
-            let found_blob = AzureBlob {
-                key: blob.name.to_owned(),
-                e_tag: blob.properties.etag.to_owned(),
-                body: None,
-                content_type: blob.properties.content_type.to_owned(),
-                content_length: blob.properties.content_length,
-                container: self.name.to_owned(),
-                storage_account: self.storage_account.to_owned(),
-            };
-            blobs.push(found_blob);
-        }
-        let nex_marker;
-        match &mut res.next_marker {
-            Some(marker) => nex_marker = Some(marker.as_str().to_owned()),
-            None => nex_marker = None,
+    /// Sets the blob's properties then its metadata, each replacing
+    /// what the blob had. The current properties are fetched first to
+    /// keep the content MD5, which setting properties would clear
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<AzureBlob> {
+        let mut properties =
+            property_headers(options).map_err(BlobError::WriteError)?;
+        let metadata =
+            metadata_headers(options).map_err(BlobError::WriteError)?;
+        let current = match self.fetch_properties(blob_path).await {
+            Ok(blob) => blob,
+            Err(details) if details.kind() == ErrorKind::NotFound => {
+                return Err(BlobError::NotFound)
+            }
+            Err(details) => return Err(BlobError::WriteError(details)),
         };
-        Ok((blobs, nex_marker))
+        properties.extend(content_md5_header(&current));
+        let (properties, metadata) = (&properties, &metadata);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                self.send_raw(
+                    blob_path,
+                    "comp=properties",
+                    properties,
+                    None,
+                    StatusCode::OK,
+                )
+                .await?;
+                self.send_raw(
+                    blob_path,
+                    "comp=metadata",
+                    metadata,
+                    None,
+                    StatusCode::OK,
+                )
+                .await
+            })
+            .await;
+        match resp {
+            Ok(_) => self.blob_properties(blob_path).await,
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
     }
 
     async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
         let resp = self
-            .client
-            .as_blob_client(blob_path)
-            .delete()
-            .execute()
+            .timeouts
+            .send(&self.retry, || async move {
+                self.client
+                    .as_blob_client(blob_path)
+                    .delete()
+                    .execute()
+                    .await
+                    .map_err(azure_error)
+            })
             .await;
         match resp {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::DeletionError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::DeletionError(e)),
         }
     }
 
-    async fn write_blob(
+    /// SAS tokens are signed with the account key, clients
+    /// authenticated with a SAS token cannot sign
+    async fn signed_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        let expiry = chrono::Duration::from_std(expires_in)
+            .ok()
+            .and_then(|expires_in| Utc::now().checked_add_signed(expires_in))
+            .ok_or_else(|| {
+                BlobError::SignError(ErrorDetails::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Expiry {:?} is out of range", expires_in),
+                ))
+            })?;
+        self.sas_url(blob_path, method, expiry)
+    }
+
+    /// Blob storage has no form uploads, browsers can
+    /// upload to a url signed for HttpMethod::Put instead
+    async fn post_policy(
+        &self,
+        _conditions: &PostConditions,
+        _expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        Err(BlobError::SignError(ErrorDetails::new(
+            ErrorKind::Unsupported,
+            "azure has no form uploads, use a signed url instead",
+        )))
+    }
+
+    async fn write_blob_with_options(
         &self,
         blob_name: &str,
         content: Option<Bytes>,
-        content_type: Option<String>,
+        options: &WriteOptions,
     ) -> BlobResult<AzureBlob> {
-        use bytes::Buf;
-        use std::io;
-
-        let mut file: Vec<u8> = Vec::new();
-        match content {
-            Some(x) => {
-                let mut reader = x.reader();
-                match io::copy(&mut reader, &mut file) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return Err(BlobError::WriteError(String::from(
-                            format!("{}", e),
-                        )))
-                    }
-                }
-            }
-            None => (),
-        }
-        let mime_type;
-        match content_type {
-            Some(c_type) => mime_type = c_type,
-            None => mime_type = "".to_owned(),
+        let content = content.unwrap_or_default();
+        if content.len() > self.block_upload.threshold {
+            let content = futures::stream::once(async { Ok(content) });
+            return self
+                .write_blob_blocks_with_options(
+                    blob_name,
+                    Box::pin(content),
+                    options,
+                )
+                .await;
         }
+        let mut headers =
+            write_headers(options).map_err(BlobError::WriteError)?;
+        headers.push(("x-ms-blob-type".to_owned(), "BlockBlob".to_owned()));
+        let (content, headers) = (&content, &headers);
         let resp = self
-            .client
-            .as_blob_client(blob_name)
-            .put_block_blob(file.clone())
-            .content_type(mime_type.as_str())
-            .execute()
+            .timeouts
+            .send(&self.retry, || async move {
+                let body = Some(content.clone());
+                self.send_raw(blob_name, "", headers, body, StatusCode::CREATED)
+                    .await
+            })
             .await;
         match resp {
-            Ok(_) => {
-                let blob = self.get_blob(blob_name, None).await;
-                match blob {
-                    Ok(blob) => Ok(blob),
-                    Err(_) => Err(BlobError::NotFound),
+            Ok(_) => self.blob_properties(blob_name).await,
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    /// Content up to the block upload threshold is uploaded in a
+    /// single request, anything larger through write_blob_blocks
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        mut content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<AzureBlob> {
+        let mut buffer = BytesMut::new();
+        while buffer.len() <= self.block_upload.threshold {
+            match content.next().await {
+                Some(chunk) => buffer.extend_from_slice(&chunk?),
+                None => {
+                    return self
+                        .write_blob_with_options(
+                            blob_name,
+                            Some(buffer.freeze()),
+                            options,
+                        )
+                        .await
                 }
             }
-            Err(e) => {
-                Err(BlobError::WriteError(String::from(format!("{}", e))))
-            }
         }
+        let buffered = buffer.freeze();
+        let content =
+            futures::stream::once(async { Ok(buffered) }).chain(content);
+        self.write_blob_blocks_with_options(
+            blob_name,
+            Box::pin(content),
+            options,
+        )
+        .await
     }
 }
 
-#[derive(Debug)]
 pub struct AzureBuckets {
     pub client: std::sync::Arc<StorageClient>,
     pub account_client: std::sync::Arc<StorageAccountClient>,
     pub storage_account: String,
+    pub block_upload: BlockUploadConfig,
+    pub retry: RetryPolicy,
+    pub timeouts: TimeoutConfig,
+    /// Kept to rebuild the client on a connect timeout
+    credentials: Credentials,
+}
+
+/// Well-known development account of Azurite and the storage emulator
+const EMULATOR_ACCOUNT: &str = "devstoreaccount1";
+const EMULATOR_ACCOUNT_KEY: &str = "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+
+/// What a client authenticates with
+#[derive(Clone)]
+enum Credentials {
+    AccessKey(String),
+    SasToken(String),
+    ConnectionString(String),
+    /// Access key of an account served under the path of endpoint
+    Endpoint {
+        endpoint: Url,
+        key: String,
+    },
+}
+
+impl Credentials {
+    fn account_client(
+        &self,
+        http_client: std::sync::Arc<dyn azure_core::HttpClient>,
+        storage_account: &str,
+    ) -> BucketResult<std::sync::Arc<StorageAccountClient>> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            BucketError::CredError(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                e.to_string(),
+            ))
+        };
+        match self {
+            Credentials::AccessKey(key) => {
+                Ok(StorageAccountClient::new_access_key(
+                    http_client,
+                    storage_account,
+                    key,
+                ))
+            }
+            Credentials::SasToken(token) => {
+                StorageAccountClient::new_sas_token(
+                    http_client,
+                    storage_account,
+                    token,
+                )
+                .map_err(|e| invalid(&e))
+            }
+            Credentials::ConnectionString(connection_string) => {
+                StorageAccountClient::new_connection_string(
+                    http_client,
+                    connection_string,
+                )
+                .map_err(|e| invalid(&e))
+            }
+            Credentials::Endpoint { endpoint, key } => {
+                Ok(StorageAccountClient::new_emulator_with_account(
+                    http_client,
+                    endpoint,
+                    endpoint,
+                    endpoint,
+                    endpoint,
+                    storage_account,
+                    key,
+                ))
+            }
+        }
+    }
+}
+
+/// The clients and credentials are left out so keys are never printed
+impl std::fmt::Debug for AzureBuckets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AzureBuckets")
+            .field("storage_account", &self.storage_account)
+            .field("block_upload", &self.block_upload)
+            .field("retry", &self.retry)
+            .field("timeouts", &self.timeouts)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AzureBuckets {
-    pub fn new(storage_account: String) -> AzureBuckets {
-        let key = std::env::var("AZURE_SECRET_ACCESS_KEY")
-            .expect("Set env variable AZURE_SECRET_ACCESS_KEY");
-        let http_client = azure_core::new_http_client();
-        let storage_account_client = StorageAccountClient::new_access_key(
-            http_client.clone(),
-            &storage_account,
-            &key,
-        );
-        AzureBuckets {
+    /// Fails when env variable AZURE_SECRET_ACCESS_KEY is not set
+    pub fn new(storage_account: String) -> BucketResult<AzureBuckets> {
+        let key = std::env::var("AZURE_SECRET_ACCESS_KEY").map_err(|_| {
+            BucketError::CredError(ErrorDetails::new(
+                ErrorKind::PermissionDenied,
+                "Set env variable AZURE_SECRET_ACCESS_KEY",
+            ))
+        })?;
+        Self::with_access_key(storage_account, key)
+    }
+
+    /// Client of storage_account authenticated with one of its keys
+    pub fn with_access_key(
+        storage_account: impl Into<String>,
+        key: impl Into<String>,
+    ) -> BucketResult<AzureBuckets> {
+        Self::with_credentials(
+            storage_account.into(),
+            Credentials::AccessKey(key.into()),
+        )
+    }
+
+    /// Client of storage_account authenticated with a shared access
+    /// signature, with or without its leading "?".
+    /// Fails when the token cannot be parsed
+    pub fn with_sas_token(
+        storage_account: impl Into<String>,
+        sas_token: impl Into<String>,
+    ) -> BucketResult<AzureBuckets> {
+        let token = sas_token.into();
+        Self::with_credentials(
+            storage_account.into(),
+            Credentials::SasToken(token.trim_start_matches('?').to_owned()),
+        )
+    }
+
+    /// Client from a connection string as found in the azure portal,
+    /// e.g "AccountName=...;AccountKey=...;EndpointSuffix=core.windows.net".
+    /// Fails when it names no account or neither a key nor a SAS token
+    pub fn with_connection_string(
+        connection_string: impl Into<String>,
+    ) -> BucketResult<AzureBuckets> {
+        let credentials =
+            Credentials::ConnectionString(connection_string.into());
+        let account_client =
+            credentials.account_client(azure_core::new_http_client(), "")?;
+        Self::with_credentials(account_client.account().to_owned(), credentials)
+    }
+
+    /// Client of storage_account served under the path of endpoint,
+    /// as emulators such as Azurite do, authenticated with an access key
+    /// e.g AzureBuckets::with_endpoint("account", key, "http://localhost:10000").
+    /// Fails when endpoint is not a valid url
+    pub fn with_endpoint(
+        storage_account: impl Into<String>,
+        key: impl Into<String>,
+        endpoint: &str,
+    ) -> BucketResult<AzureBuckets> {
+        let mut endpoint = Url::parse(endpoint).map_err(|e| {
+            BucketError::CredError(ErrorDetails::from_source(
+                ErrorKind::InvalidArgument,
+                e,
+            ))
+        })?;
+        // the account is appended to the endpoint's path
+        if !endpoint.path().ends_with('/') {
+            endpoint.set_path(&format!("{}/", endpoint.path()));
+        }
+        Self::with_credentials(
+            storage_account.into(),
+            Credentials::Endpoint {
+                endpoint,
+                key: key.into(),
+            },
+        )
+    }
+
+    /// Client of the well-known development account of an Azurite
+    /// blob service at endpoint e.g "http://127.0.0.1:10000"
+    pub fn emulator(endpoint: &str) -> BucketResult<AzureBuckets> {
+        Self::with_endpoint(EMULATOR_ACCOUNT, EMULATOR_ACCOUNT_KEY, endpoint)
+    }
+
+    fn with_credentials(
+        storage_account: String,
+        credentials: Credentials,
+    ) -> BucketResult<AzureBuckets> {
+        let storage_account_client = credentials
+            .account_client(azure_core::new_http_client(), &storage_account)?;
+        Ok(AzureBuckets {
             client: storage_account_client.as_storage_client(),
             account_client: storage_account_client,
             storage_account,
+            block_upload: BlockUploadConfig::default(),
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
+            credentials,
+        })
+    }
+
+    /// Set how blobs of containers opened from here are uploaded in blocks
+    pub fn with_block_upload_config(
+        mut self,
+        config: BlockUploadConfig,
+    ) -> Self {
+        self.block_upload = config;
+        self
+    }
+
+    /// Set how failed requests of this client, the containers opened
+    /// from here and their blobs are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set how long requests of this client, the containers opened
+    /// from here and their blobs may take.
+    /// A connect timeout replaces the client with one using it
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        if let Some(connect) = config.connect {
+            // building only fails when no TLS backend can be loaded and
+            // the credentials already built a client, it is kept then
+            let account_client = reqwest::Client::builder()
+                .connect_timeout(connect)
+                .build()
+                .ok()
+                .and_then(|http_client| {
+                    self.credentials
+                        .account_client(
+                            std::sync::Arc::new(http_client),
+                            &self.storage_account,
+                        )
+                        .ok()
+                });
+            if let Some(account_client) = account_client {
+                self.client = account_client.as_storage_client();
+                self.account_client = account_client;
+            }
+        }
+        self.timeouts = config;
+        self
+    }
+
+    /// Container of this client without checking that it exists
+    pub(crate) fn bucket(&self, container: &str) -> AzureBucket {
+        AzureBucket {
+            name: container.to_owned(),
+            client: self.client.as_container_client(container),
+            account_client: self.account_client.clone(),
+            storage_account: self.storage_account.clone(),
+            block_upload: self.block_upload,
+            retry: self.retry,
+            timeouts: self.timeouts,
+        }
+    }
+
+    /// Container properties in a single request, 404 and 403
+    /// responses are told apart
+    async fn container_properties(
+        &self,
+        bucket_name: &str,
+    ) -> BucketResult<()> {
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                self.client
+                    .as_container_client(bucket_name)
+                    .get_properties()
+                    .execute()
+                    .await
+                    .map_err(azure_error)
+            })
+            .await;
+        match resp {
+            Ok(_) => Ok(()),
+            Err(details) => match details.kind() {
+                ErrorKind::NotFound => Err(BucketError::NotFound),
+                ErrorKind::PermissionDenied => {
+                    Err(BucketError::PermissionDenied(details))
+                }
+                _ => Err(BucketError::OpenError(details)),
+            },
         }
     }
 }
 
 #[async_trait]
 impl Buckets<AzureBucket, AzureBlob> for AzureBuckets {
-    async fn list(&mut self) -> Vec<AzureBucket> {
-        let response = self
-            .client
-            .as_blob_service_client()
-            .list_containers()
-            .include_metadata(true)
-            .execute()
-            .await;
+    async fn list(&mut self) -> BucketResult<Vec<AzureBucket>> {
+        let client = &self.client;
         let mut buckets: Vec<AzureBucket> = Vec::new();
-        for bucket in response.unwrap().incomplete_vector.iter() {
-            let bucket_found = AzureBucket {
-                name: bucket.name.clone(),
-                client: self.client.as_container_client(&bucket.name),
-                storage_account: self.storage_account.clone(),
-            };
-            buckets.push(bucket_found);
-        }
-        buckets
-    }
-
-    async fn exists(&mut self, bucket_name: &str) -> bool {
-        let containers = self.client
-            .as_blob_service_client()
-            .list_containers()
-            .execute().await;
-        containers
-            .unwrap()
-            .incomplete_vector
-            .iter()
-            .find(|item| item.name == bucket_name)
-            .is_some()
+        let mut marker: Option<NextMarker> = None;
+        // containers are listed a page at a time, follow the
+        // continuation marker until the last page
+        loop {
+            let page = &marker;
+            let response = self
+                .timeouts
+                .send(&self.retry, || async move {
+                    let service = client.as_blob_service_client();
+                    let mut request =
+                        service.list_containers().include_metadata(true);
+                    if let Some(marker) = page {
+                        request = request.next_marker(marker.clone());
+                    }
+                    request.execute().await.map_err(azure_error)
+                })
+                .await
+                .map_err(BucketError::ListError)?;
+            for bucket in response.incomplete_vector.iter() {
+                buckets.push(self.bucket(&bucket.name));
+            }
+            marker = response.incomplete_vector.next_marker().cloned();
+            if marker.is_none() {
+                return Ok(buckets);
+            }
+        }
+    }
+
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        match self.container_properties(bucket_name).await {
+            Ok(_) => Ok(true),
+            Err(BucketError::NotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     async fn create(
@@ -378,39 +1349,41 @@ impl Buckets<AzureBucket, AzureBlob> for AzureBuckets {
         bucket_name: &str,
         _location: Option<String>,
     ) -> BucketResult<AzureBucket> {
-        match self
-            .client
-            .as_container_client(bucket_name)
-            .create()
-            .public_access(PublicAccess::None)
-            .timeout(Duration::from_secs(100))
-            .execute()
-            .await
-        {
-            Ok(_) => Ok(AzureBucket {
-                name: bucket_name.to_owned(),
-                client: self.client.as_container_client(bucket_name),
-                storage_account: self.storage_account.clone(),
-            }),
-            Err(e) => {
-                Err(BucketError::CreationError(String::from(format!("{}", e))))
-            }
+        let resp = self
+            .timeouts
+            .send_once(async {
+                self.client
+                    .as_container_client(bucket_name)
+                    .create()
+                    .public_access(PublicAccess::None)
+                    .execute()
+                    .await
+                    .map_err(azure_error)
+            })
+            .await;
+        match resp {
+            Ok(_) => Ok(self.bucket(bucket_name)),
+            Err(e) => Err(BucketError::CreationError(e)),
         }
     }
 
     async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool> {
-        if self.exists(bucket_name).await {
-            match self
-                .client
-                .as_container_client(bucket_name)
-                .delete()
-                .execute()
-                .await
-            {
+        if self.exists(bucket_name).await? {
+            let client = &self.client;
+            let resp = self
+                .timeouts
+                .send(&self.retry, || async move {
+                    client
+                        .as_container_client(bucket_name)
+                        .delete()
+                        .execute()
+                        .await
+                        .map_err(azure_error)
+                })
+                .await;
+            match resp {
                 Ok(_) => Ok(true),
-                Err(e) => Err(BucketError::DeletionError(String::from(
-                    format!("{}", e),
-                ))),
+                Err(e) => Err(BucketError::DeletionError(e)),
             }
         } else {
             Ok(false)
@@ -418,22 +1391,119 @@ impl Buckets<AzureBucket, AzureBlob> for AzureBuckets {
     }
 
     async fn open(&mut self, bucket_name: &str) -> BucketResult<AzureBucket> {
-        let response = self.client
-            .as_blob_service_client()
-            .list_containers()
-            .execute().await;
-        match response
-            .unwrap()
-            .incomplete_vector
-            .iter()
-            .find(|item| item.name == bucket_name)
-        {
-            Some(container) => Ok(AzureBucket {
-                name: container.name.clone(),
-                client: self.client.as_container_client(&container.name),
-                storage_account: self.storage_account.clone(),
-            }),
-            None => Err(BucketError::NotFound),
+        self.container_properties(bucket_name).await?;
+        Ok(self.bucket(bucket_name))
+    }
+}
+
+/// Headers setting the properties of options
+fn property_headers(
+    options: &WriteOptions,
+) -> Result<Vec<(String, String)>, ErrorDetails> {
+    let properties = [
+        ("x-ms-blob-content-type", &options.content_type),
+        ("x-ms-blob-cache-control", &options.cache_control),
+        ("x-ms-blob-content-encoding", &options.content_encoding),
+        (
+            "x-ms-blob-content-disposition",
+            &options.content_disposition,
+        ),
+        ("x-ms-blob-content-language", &options.content_language),
+    ];
+    let headers = properties
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_ref()
+                .map(|value| (name.to_string(), value.clone()))
+        })
+        .collect();
+    printable(headers)
+}
+
+/// Headers setting the user metadata of options.
+/// Metadata keys must be C# identifiers
+fn metadata_headers(
+    options: &WriteOptions,
+) -> Result<Vec<(String, String)>, ErrorDetails> {
+    let mut headers = Vec::new();
+    for (key, value) in &options.metadata {
+        let identifier = key
+            .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !identifier {
+            return Err(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid metadata key {}", key),
+            ));
         }
+        headers.push((format!("x-ms-meta-{}", key), value.clone()));
     }
+    printable(headers)
+}
+
+/// Header keeping the content MD5 of blob when its properties are set
+fn content_md5_header(
+    blob: &azure_storage_blobs::blob::Blob,
+) -> Option<(String, String)> {
+    blob.properties.content_md5.as_ref().map(|md5| {
+        (
+            "x-ms-blob-content-md5".to_owned(),
+            base64::encode(md5.as_ref()),
+        )
+    })
+}
+
+/// Headers whose values are all printable ASCII, the only values
+/// requests can be signed with
+fn printable(
+    headers: Vec<(String, String)>,
+) -> Result<Vec<(String, String)>, ErrorDetails> {
+    let printable = headers
+        .iter()
+        .all(|(_, value)| value.bytes().all(|b| (b' '..=b'~').contains(&b)));
+    if !printable {
+        return Err(ErrorDetails::new(
+            ErrorKind::InvalidArgument,
+            "Properties and metadata must be printable ASCII",
+        ));
+    }
+    Ok(headers)
+}
+
+/// Headers of a blob written with options, its access tier included
+fn write_headers(
+    options: &WriteOptions,
+) -> Result<Vec<(String, String)>, ErrorDetails> {
+    let mut headers = property_headers(options)?;
+    headers.extend(metadata_headers(options)?);
+    if let Some(ref tier) = options.storage_class {
+        headers.push(("x-ms-access-tier".to_owned(), tier.clone()));
+    }
+    Ok(headers)
+}
+
+/// Details of a failed request, Azure only reports the request id
+/// within the body of error responses e.g "RequestId:<id>"
+fn azure_error(error: ErrorSource) -> ErrorDetails {
+    let details = match error.downcast_ref::<azure_core::HttpError>() {
+        Some(azure_core::HttpError::StatusCode { status, body }) => {
+            let details = ErrorDetails::from_status(status.as_u16(), body);
+            let request_id = body
+                .split("RequestId:")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next());
+            match request_id {
+                Some(request_id) => details.with_request_id(request_id),
+                None => details,
+            }
+        }
+        Some(
+            azure_core::HttpError::ExecuteRequest(_)
+            | azure_core::HttpError::ReadBytes(_)
+            | azure_core::HttpError::StreamReset(_),
+        ) => ErrorDetails::new(ErrorKind::Connection, error.to_string()),
+        _ => ErrorDetails::new(ErrorKind::Other, error.to_string()),
+    };
+    details.with_source(error)
 }
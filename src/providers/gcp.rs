@@ -1,48 +1,413 @@
-use crate::types::blob::Blob;
-use crate::types::bucket::{Bucket, Buckets};
-use crate::types::errors::{BlobError, BlobResult, BucketError, BucketResult};
+use crate::providers::chunked;
+use crate::providers::gcp_auth::{AuthResult, Credentials};
+use crate::providers::{
+    check_expiry, hex, post_policy_document, split_destination,
+};
+use crate::types::blob::{
+    Blob, BlobProperties, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
+use crate::types::errors::{
+    BlobError, BlobResult, BucketError, BucketResult, ErrorDetails, ErrorKind,
+};
+use crate::types::retry::RetryPolicy;
+use crate::types::timeout::TimeoutConfig;
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
 use futures::StreamExt;
-use regex::Regex;
 
-use cloud_storage::bucket::NewBucket;
 use cloud_storage::object::ObjectList;
-use cloud_storage::Client;
-use cloud_storage::ListRequest;
-use cloud_storage::Result as CResult;
+use reqwest::header::{
+    HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, LOCATION, RANGE,
+};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Endpoint requests are sent to unless one is set with with_endpoint
+const ENDPOINT: &str = "https://storage.googleapis.com";
+/// Path of the JSON API bucket resources
+const BUCKETS_PATH: &str = "/storage/v1/b";
+/// Path uploads are made to
+const UPLOAD_PATH: &str = "/upload/storage/v1/b";
+/// Chunks of a resumable upload, except the last, must be
+/// a multiple of this size
+const CHUNK_ALIGNMENT: usize = 256 * 1024;
+
+/// Fields of an object resource set when it is uploaded
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectResource<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_encoding: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_disposition: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage_class: Option<&'a str>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: &'a HashMap<String, String>,
+}
+
+impl<'a> From<&'a WriteOptions> for ObjectResource<'a> {
+    fn from(options: &'a WriteOptions) -> Self {
+        ObjectResource {
+            content_type: options.content_type.as_deref(),
+            cache_control: options.cache_control.as_deref(),
+            content_encoding: options.content_encoding.as_deref(),
+            content_disposition: options.content_disposition.as_deref(),
+            content_language: options.content_language.as_deref(),
+            storage_class: options.storage_class.as_deref(),
+            metadata: &options.metadata,
+        }
+    }
+}
+
+/// Progress of a rewrite, the object is only returned once it is done
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RewriteResponse {
+    rewrite_token: Option<String>,
+    resource: Option<cloud_storage::Object>,
+}
+
+/// Fields of an object resource replaced by update_blob_metadata,
+/// null clears a header and a null metadata value removes the key
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectPatch<'a> {
+    content_type: Option<&'a str>,
+    cache_control: Option<&'a str>,
+    content_encoding: Option<&'a str>,
+    content_disposition: Option<&'a str>,
+    content_language: Option<&'a str>,
+    metadata: HashMap<&'a str, Option<&'a str>>,
+}
+
+impl<'a> ObjectPatch<'a> {
+    /// Patch giving an object the headers and metadata of options,
+    /// removing the keys of current metadata options does not hold
+    fn new(
+        options: &'a WriteOptions,
+        current: &'a HashMap<String, String>,
+    ) -> Self {
+        let mut metadata: HashMap<&str, Option<&str>> =
+            current.keys().map(|key| (key.as_str(), None)).collect();
+        for (key, value) in &options.metadata {
+            metadata.insert(key, Some(value));
+        }
+        ObjectPatch {
+            content_type: options.content_type.as_deref(),
+            cache_control: options.cache_control.as_deref(),
+            content_encoding: options.content_encoding.as_deref(),
+            content_disposition: options.content_disposition.as_deref(),
+            content_language: options.content_language.as_deref(),
+            metadata,
+        }
+    }
+}
+
+/// Body of a multipart upload: the object resource as JSON followed
+/// by the content, returned with the boundary separating them
+fn multipart_body(
+    options: &WriteOptions,
+    content: &[u8],
+) -> BlobResult<(String, Bytes)> {
+    let resource = serde_json::to_string(&ObjectResource::from(options))
+        .map_err(|e| {
+            BlobError::WriteError(ErrorDetails::from_source(
+                ErrorKind::Other,
+                e,
+            ))
+        })?;
+    // random so that it cannot occur in the content
+    let mut nonce = [0; 16];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| {
+        BlobError::WriteError("Could not generate a boundary".into())
+    })?;
+    let boundary = format!("waihona-{}", hex(&nonce));
+    let content_type = options
+        .content_type
+        .as_deref()
+        .unwrap_or("application/octet-stream");
+    let mut body = BytesMut::with_capacity(content.len() + 512);
+    body.extend_from_slice(
+        format!(
+            "--{b}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n\
+             {}\r\n--{b}\r\nContent-Type: {}\r\n\r\n",
+            resource,
+            content_type,
+            b = boundary
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    Ok((boundary, body.freeze()))
+}
+
+/// Controls when and how blobs are uploaded through resumable sessions
+#[derive(Debug, Clone, Copy)]
+pub struct ResumableConfig {
+    /// Size of each chunk, rounded up to a multiple of 256KiB
+    pub chunk_size: usize,
+    /// Blobs larger than this are uploaded through a resumable session
+    pub threshold: usize,
+}
+
+impl Default for ResumableConfig {
+    fn default() -> Self {
+        ResumableConfig {
+            chunk_size: 8 * 1024 * 1024,
+            threshold: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Send req authenticated as credentials, or anonymously without,
+/// responses with a failure status are turned into errors.
+/// Tokens are requested through http
+async fn send(
+    credentials: Option<&Credentials>,
+    http: &reqwest::Client,
+    mut req: RequestBuilder,
+) -> Result<Response, ErrorDetails> {
+    if let Some(credentials) = credentials {
+        req = req.bearer_auth(credentials.access_token(http).await?);
+    }
+    let resp = req.send().await?;
+    if resp.status().is_success() {
+        Ok(resp)
+    } else {
+        Err(error_details(resp).await)
+    }
+}
+
+/// Resource a JSON API request responded with
+async fn json<T: DeserializeOwned>(resp: Response) -> Result<T, ErrorDetails> {
+    let raw = resp.bytes().await?;
+    serde_json::from_slice(&raw)
+        .map_err(|e| ErrorDetails::from_source(ErrorKind::Other, e))
+}
+
+fn cred_details(
+    error: Box<dyn std::error::Error + Send + Sync>,
+) -> ErrorDetails {
+    ErrorDetails::from_source(ErrorKind::PermissionDenied, error)
+}
+
+/// Page of a bucket listing
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BucketList {
+    #[serde(default)]
+    items: Vec<cloud_storage::Bucket>,
+    next_page_token: Option<String>,
+}
 
 #[derive(Debug)]
 pub struct GcpBuckets {
-    pub client: Client,
+    /// None for emulators accepting anonymous requests
+    credentials: Option<Arc<Credentials>>,
+    /// Shared with the buckets opened from here and their blobs
+    http: reqwest::Client,
+    endpoint: String,
     pub user_project: String,
+    pub resumable: ResumableConfig,
+    pub retry: RetryPolicy,
+    pub timeouts: TimeoutConfig,
 }
 
 /// project name on GCP
 /// Will make use of the exported credential at
 /// GOOGLE_APPLICATION_CREDENTIALS
 impl GcpBuckets {
-    pub fn new(project_name: impl Into<String>) -> GcpBuckets {
+    /// Fails when no service account credentials are set
+    pub fn new(project_name: impl Into<String>) -> BucketResult<GcpBuckets> {
+        Self::with_credentials(project_name.into(), Credentials::from_env())
+    }
+
+    /// Client authenticated as the service account of a key file's
+    /// content, an empty project name uses the service account's own.
+    /// Fails when the content is not a service account key
+    pub fn with_service_account_json(
+        project_name: impl Into<String>,
+        credentials_json: &str,
+    ) -> BucketResult<GcpBuckets> {
+        Self::with_credentials(
+            project_name.into(),
+            Credentials::from_json(credentials_json).map(Arc::new),
+        )
+    }
+
+    fn with_credentials(
+        user_project: String,
+        credentials: AuthResult<Arc<Credentials>>,
+    ) -> BucketResult<GcpBuckets> {
+        let credentials =
+            credentials.map_err(|e| BucketError::CredError(cred_details(e)))?;
+        Ok(GcpBuckets {
+            credentials: Some(credentials),
+            http: reqwest::Client::new(),
+            endpoint: ENDPOINT.to_owned(),
+            user_project,
+            resumable: ResumableConfig::default(),
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
+        })
+    }
+
+    /// Anonymous client of an emulator such as fake-gcs-server,
+    /// e.g GcpBuckets::emulator("test", "http://localhost:4443")
+    pub fn emulator(
+        project_name: impl Into<String>,
+        endpoint: impl Into<String>,
+    ) -> GcpBuckets {
         GcpBuckets {
-            client: Client::default(),
+            credentials: None,
+            http: reqwest::Client::new(),
+            endpoint: ENDPOINT.to_owned(),
             user_project: project_name.into(),
+            resumable: ResumableConfig::default(),
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
+        }
+        .with_endpoint(endpoint)
+    }
+
+    /// Send requests of this client, the buckets opened from here and
+    /// their blobs to endpoint instead of storage.googleapis.com
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Set how blobs of buckets opened from here are uploaded
+    /// through resumable sessions
+    pub fn with_resumable_config(mut self, config: ResumableConfig) -> Self {
+        self.resumable = config;
+        self
+    }
+
+    /// Set how failed requests of this client, the buckets opened
+    /// from here and their blobs are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set how long requests of this client, the buckets opened
+    /// from here and their blobs may take.
+    /// A connect timeout replaces the client with one using it
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        if let Some(connect) = config.connect {
+            // building only fails when no TLS backend can be loaded,
+            // the current client is kept then
+            if let Ok(http) =
+                reqwest::Client::builder().connect_timeout(connect).build()
+            {
+                self.http = http;
+            }
+        }
+        self.timeouts = config;
+        self
+    }
+
+    /// Project buckets are listed and created in
+    fn project(&self) -> &str {
+        match self.credentials {
+            Some(ref credentials) if self.user_project.is_empty() => {
+                credentials.project_id()
+            }
+            _ => &self.user_project,
+        }
+    }
+
+    fn buckets_url(&self) -> String {
+        format!("{}{}", self.endpoint, BUCKETS_PATH)
+    }
+
+    fn bucket(&self, bucket: &cloud_storage::Bucket) -> GcpBucket {
+        GcpBucket {
+            e_tag: bucket.etag.clone(),
+            self_link: bucket.self_link.clone(),
+            ..self.bucket_named(&bucket.name)
+        }
+    }
+
+    /// Bucket of this client without checking that it exists
+    pub(crate) fn bucket_named(&self, name: &str) -> GcpBucket {
+        GcpBucket {
+            name: name.to_owned(),
+            credentials: self.credentials.clone(),
+            http: self.http.clone(),
+            endpoint: self.endpoint.clone(),
+            user_project: self.user_project.clone(),
+            e_tag: String::new(),
+            self_link: String::new(),
+            resumable: self.resumable,
+            retry: self.retry,
+            timeouts: self.timeouts,
+        }
+    }
+
+    /// Bucket metadata in a single request, 404 and 403 responses
+    /// are told apart
+    async fn read_bucket(
+        &self,
+        bucket_name: &str,
+    ) -> BucketResult<cloud_storage::Bucket> {
+        let url =
+            format!("{}/{}", self.buckets_url(), percent_encode(bucket_name));
+        let (credentials, http, url) =
+            (self.credentials.as_deref(), &self.http, &url);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                json(send(credentials, http, http.get(url)).await?).await
+            })
+            .await;
+        match resp {
+            Ok(bucket) => Ok(bucket),
+            Err(details) => match details.kind() {
+                ErrorKind::NotFound => Err(BucketError::NotFound),
+                ErrorKind::PermissionDenied => {
+                    Err(BucketError::PermissionDenied(details))
+                }
+                _ => Err(BucketError::OpenError(details)),
+            },
         }
     }
 }
 
 #[derive(Debug)]
 pub struct GcpBlob {
-    key: Option<String>,
-    e_tag: Option<String>,
-    size: Option<i64>,
+    properties: BlobProperties,
     body: Option<Vec<u8>>,
-    content_type: Option<String>,
     content_range: Option<String>,
-    bucket: String,
     project: String,
+    /// Bucket the blob comes from, opened from the environment
+    /// when the blob was built through new
+    bucket: Option<GcpBucket>,
+    retry: RetryPolicy,
+    timeouts: TimeoutConfig,
 }
 
 impl GcpBlob {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         key: Option<String>,
         e_tag: Option<String>,
@@ -54,14 +419,44 @@ impl GcpBlob {
         project: String,
     ) -> Self {
         GcpBlob {
-            key,
-            e_tag,
-            size,
+            properties: BlobProperties {
+                e_tag,
+                size: size.map(|size| size as u64),
+                content_type,
+                ..BlobProperties::new(key.unwrap_or_default(), bucket)
+            },
             body,
-            content_type,
             content_range,
-            bucket,
             project,
+            bucket: None,
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
+        }
+    }
+
+    fn from_object(obj: cloud_storage::Object, project: String) -> Self {
+        GcpBlob {
+            properties: BlobProperties {
+                key: obj.name,
+                bucket: obj.bucket,
+                size: Some(obj.size),
+                e_tag: Some(obj.etag),
+                content_type: obj.content_type,
+                content_encoding: obj.content_encoding,
+                cache_control: obj.cache_control,
+                content_disposition: obj.content_disposition,
+                content_language: obj.content_language,
+                last_modified: Some(obj.updated),
+                created: Some(obj.time_created),
+                storage_class: Some(obj.storage_class),
+                metadata: obj.metadata.unwrap_or_default(),
+            },
+            body: None,
+            content_range: None,
+            project,
+            bucket: None,
+            retry: RetryPolicy::default(),
+            timeouts: TimeoutConfig::default(),
         }
     }
 
@@ -69,26 +464,52 @@ impl GcpBlob {
         project_name: &str,
         bucket: &str,
         blob_path: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<Self> {
-        let mut buckets = GcpBuckets::new(project_name);
+        let mut buckets = GcpBuckets::new(project_name)
+            .map_err(|e| BlobError::GetError(e.into()))?;
         let bucket = buckets.open(bucket).await;
         match bucket {
-            Ok(b) => b.get_blob(blob_path, content_range).await,
-            Err(e) => Err(BlobError::GetError(e.to_string())),
+            Ok(b) => b.get_blob(blob_path, range).await,
+            Err(e) => Err(BlobError::GetError(e.into())),
+        }
+    }
+}
+
+impl GcpBlob {
+    /// Bucket the blob belongs to, with the credentials, retry
+    /// policy and timeouts of the one it comes from
+    async fn bucket(&self) -> BucketResult<GcpBucket> {
+        if let Some(ref bucket) = self.bucket {
+            return Ok(bucket.clone());
         }
+        GcpBuckets::new(&self.project)?
+            .with_retry_policy(self.retry)
+            .with_timeout_config(self.timeouts)
+            .open(&self.properties.bucket)
+            .await
     }
 }
 
 #[async_trait]
 impl Blob for GcpBlob {
+    fn properties(&self) -> &BlobProperties {
+        &self.properties
+    }
+
+    fn content_range(&self) -> Option<&str> {
+        self.content_range.as_deref()
+    }
+
     async fn delete(&self) -> BlobResult<bool> {
-        let mut buckets = GcpBuckets::new(&self.project);
-        let bucket = buckets.open(&self.bucket).await.unwrap();
-        let del = bucket.delete_blob(&self.key.as_ref().unwrap()).await;
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::DeletionError(e.into()))?;
+        let del = bucket.delete_blob(&self.properties.key).await;
         match del {
             Ok(_) => Ok(true),
-            Err(e) => Err(BlobError::CopyError(String::from(format!("{}", e)))),
+            Err(e) => Err(BlobError::DeletionError(e.into())),
         }
     }
 
@@ -97,88 +518,162 @@ impl Blob for GcpBlob {
         blob_destination_path: &str,
         content_type: Option<String>,
     ) -> BlobResult<bool> {
-        let mut buckets = GcpBuckets::new(&self.project);
-        let bucket = buckets.open(&self.bucket).await.unwrap();
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::CopyError(e.into()))?;
         let copied = bucket
             .copy_blob(
-                &self.key.as_ref().unwrap(),
+                &self.properties.key,
                 blob_destination_path,
                 content_type,
             )
             .await;
         match copied {
             Ok(_) => Ok(true),
-            Err(e) => Err(BlobError::CopyError(String::from(format!("{}", e)))),
+            Err(e) => Err(BlobError::CopyError(e.into())),
         }
     }
 
-    async fn write(
+    async fn write_with_options(
         &self,
         content: Option<Bytes>,
-        content_type: Option<String>,
+        options: &WriteOptions,
     ) -> BlobResult<bool> {
-        let mut buckets = GcpBuckets::new(&self.project);
-        let bucket = buckets.open(&self.bucket).await.unwrap();
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))?;
         let write = bucket
-            .write_blob(&self.key.as_ref().unwrap(), content, content_type)
+            .write_blob_with_options(&self.properties.key, content, options)
             .await;
         match write {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::WriteError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::WriteError(e.into())),
         }
     }
 
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()> {
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))?;
+        let updated = bucket
+            .update_blob_metadata(&self.properties.key, options)
+            .await?;
+        self.properties = updated.properties;
+        Ok(())
+    }
+
+    /// Returns the content fetched by a ranged get_blob, blobs
+    /// obtained any other way download the blob
     async fn read(&mut self) -> BlobResult<Bytes> {
-        let buckets = GcpBuckets::new(&self.project);
-        let resp = buckets
-            .client
-            .object()
-            .download(&self.bucket, &self.key.as_ref().unwrap())
+        if let Some(ref body) = self.body {
+            return Ok(Bytes::from(body.clone()));
+        }
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?;
+        let url =
+            format!("{}?alt=media", bucket.object_url(&self.properties.key));
+        let (bucket, url) = (&bucket, &url);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let resp = send(
+                    bucket.credentials.as_deref(),
+                    &bucket.http,
+                    bucket.http.get(url),
+                )
+                .await?;
+                Ok(resp.bytes().await?)
+            })
             .await;
         match resp {
-            Ok(res) => Ok(Bytes::from(res)),
-            Err(_) => Err(BlobError::ReadError),
+            Ok(res) => Ok(res),
+            Err(e) => Err(BlobError::ReadError(e)),
         }
     }
+
+    async fn read_stream(&mut self) -> BlobResult<BlobStream> {
+        if let Some(body) = self.body.take() {
+            let body = Bytes::from(body);
+            return Ok(Box::pin(futures::stream::once(async { Ok(body) })));
+        }
+        let bucket = self
+            .bucket()
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?;
+        let url =
+            format!("{}?alt=media", bucket.object_url(&self.properties.key));
+        let (bucket, url) = (&bucket, &url);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                send(
+                    bucket.credentials.as_deref(),
+                    &bucket.http,
+                    bucket.http.get(url),
+                )
+                .await
+            })
+            .await
+            .map_err(BlobError::ReadError)?;
+        let chunks = resp
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(|e| BlobError::ReadError(e.into())));
+        Ok(Box::pin(chunks))
+    }
+
+    async fn signed_url(
+        &self,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        self.bucket()
+            .await
+            .map_err(|e| BlobError::SignError(e.into()))?
+            .signed_url(&self.properties.key, method, expires_in)
+            .await
+    }
 }
 
 #[async_trait]
 impl Buckets<GcpBucket, GcpBlob> for GcpBuckets {
-    async fn list(&mut self) -> Vec<GcpBucket> {
-        let resp = self.client.bucket().list().await;
-        let mut buckets: Vec<GcpBucket> = Vec::new();
-        for bucket in resp.unwrap().iter() {
-            let bucket_found = GcpBucket {
-                name: bucket.name.clone(),
-                client: Client::default(),
-                user_project: self.user_project.clone(),
-                e_tag: bucket.etag.clone(),
-                self_link: bucket.self_link.clone(),
-            };
-            buckets.push(bucket_found);
+    async fn list(&mut self) -> BucketResult<Vec<GcpBucket>> {
+        let mut buckets = Vec::new();
+        let mut page_token: Option<String> = None;
+        let (credentials, http, project) =
+            (self.credentials.as_deref(), &self.http, self.project());
+        let url = &self.buckets_url();
+        loop {
+            let token = &page_token;
+            let page: BucketList = self
+                .timeouts
+                .send(&self.retry, || async move {
+                    let mut req = http.get(url).query(&[("project", project)]);
+                    if let Some(token) = token {
+                        req = req.query(&[("pageToken", token)]);
+                    }
+                    json(send(credentials, http, req).await?).await
+                })
+                .await
+                .map_err(BucketError::ListError)?;
+            buckets.extend(page.items.iter().map(|bucket| self.bucket(bucket)));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(buckets),
+            }
         }
-        buckets
     }
 
     async fn open(&mut self, bucket_name: &str) -> BucketResult<GcpBucket> {
-        if self.exists(bucket_name).await {
-            match self.client.bucket().read(bucket_name).await {
-                Ok(b) => Ok(GcpBucket {
-                    name: b.name.clone(),
-                    client: Client::default(),
-                    user_project: self.user_project.clone(),
-                    e_tag: b.etag.clone(),
-                    self_link: b.self_link.clone(),
-                }),
-                Err(_) => Err(BucketError::OpenError(String::from(
-                    "Could not open bucket",
-                ))),
-            }
-        } else {
-            Err(BucketError::NotFound)
-        }
+        let bucket = self.read_bucket(bucket_name).await?;
+        Ok(self.bucket(&bucket))
     }
 
     async fn create(
@@ -186,60 +681,672 @@ impl Buckets<GcpBucket, GcpBlob> for GcpBuckets {
         bucket_name: &str,
         _location: Option<String>,
     ) -> BucketResult<GcpBucket> {
-        let new_bucket = NewBucket {
-            name: bucket_name.to_string(),
-            ..Default::default()
-        };
-        let resp = self.client.bucket().create(&new_bucket).await;
+        let new_bucket = serde_json::json!({ "name": bucket_name });
+        let req = self
+            .http
+            .post(self.buckets_url())
+            .query(&[("project", self.project())])
+            .header(CONTENT_TYPE, "application/json")
+            .body(new_bucket.to_string());
+        let resp = self
+            .timeouts
+            .send_once(async {
+                json(send(self.credentials.as_deref(), &self.http, req).await?)
+                    .await
+            })
+            .await;
         match resp {
-            Ok(a) => Ok(GcpBucket {
-                name: a.name.clone(),
-                client: Client::default(),
-                user_project: self.user_project.clone(),
-                e_tag: a.etag.clone(),
-                self_link: a.self_link.clone(),
-            }),
-            Err(e) => {
-                Err(BucketError::CreationError(String::from(format!("{}", e))))
-            }
+            Ok(a) => Ok(self.bucket(&a)),
+            Err(e) => Err(BucketError::CreationError(e)),
         }
     }
 
     async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool> {
-        if self.exists(bucket_name).await {
-            let bucket = self.client.bucket().read(bucket_name).await.unwrap();
-            match self.client.bucket().delete(bucket).await {
-                Ok(_) => Ok(true),
-                Err(e) => Err(BucketError::DeletionError(String::from(
-                    format!("{}", e),
-                ))),
-            }
-        } else {
-            Ok(false)
+        let url =
+            format!("{}/{}", self.buckets_url(), percent_encode(bucket_name));
+        let (credentials, http, url) =
+            (self.credentials.as_deref(), &self.http, &url);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                send(credentials, http, http.delete(url)).await
+            })
+            .await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(details) => match details.kind() {
+                ErrorKind::NotFound => Ok(false),
+                ErrorKind::PermissionDenied => {
+                    Err(BucketError::PermissionDenied(details))
+                }
+                _ => Err(BucketError::DeletionError(details)),
+            },
         }
     }
-    async fn exists(&mut self, bucket_name: &str) -> bool {
-        match self.client.bucket().read(bucket_name).await {
-            Ok(_) => true,
-            Err(_) => false,
+
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        match self.read_bucket(bucket_name).await {
+            Ok(_) => Ok(true),
+            Err(BucketError::NotFound) => Ok(false),
+            Err(e) => Err(e),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GcpBucket {
     pub name: String,
-    pub client: Client,
+    credentials: Option<Arc<Credentials>>,
+    http: reqwest::Client,
+    endpoint: String,
     pub user_project: String,
     pub e_tag: String,
     pub self_link: String,
+    pub resumable: ResumableConfig,
+    pub retry: RetryPolicy,
+    pub timeouts: TimeoutConfig,
 }
 
 impl GcpBucket {
-    pub async fn exists(project: &str, bucket: &str) -> bool {
-        let mut buckets = GcpBuckets::new(project);
+    /// Service account urls and forms are signed as, with the
+    /// scheme and host of the endpoint they are sent to
+    fn signer(&self) -> BlobResult<(&Credentials, String, String)> {
+        let sign_error = |kind, message: String| {
+            BlobError::SignError(ErrorDetails::new(kind, message))
+        };
+        let credentials = self.credentials.as_deref().ok_or_else(|| {
+            sign_error(
+                ErrorKind::Unsupported,
+                "Anonymous clients cannot sign".to_owned(),
+            )
+        })?;
+        let endpoint = url::Url::parse(&self.endpoint).map_err(|e| {
+            sign_error(ErrorKind::InvalidArgument, e.to_string())
+        })?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => {
+                return Err(sign_error(
+                    ErrorKind::InvalidArgument,
+                    format!("No host in endpoint {}", self.endpoint),
+                ))
+            }
+        };
+        Ok((credentials, endpoint.scheme().to_owned(), host))
+    }
+
+    /// V4 signed url granting method on blob_path for expires_in from
+    /// now, see https://cloud.google.com/storage/docs/access-control/signing-urls-manually
+    pub(crate) fn signed_url_at(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+        now: DateTime<Utc>,
+    ) -> BlobResult<String> {
+        check_expiry(expires_in)?;
+        let (credentials, scheme, host) = self.signer()?;
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let scope = format!("{}/auto/storage/goog4_request", &timestamp[..8]);
+        let credential = format!("{}/{}", credentials.client_email(), scope);
+        let path = format!(
+            "/{}/{}",
+            sign_encode(&self.name, V4_PATH),
+            sign_encode(blob_path, V4_PATH)
+        );
+        // parameters sorted by name
+        let query = [
+            ("X-Goog-Algorithm", "GOOG4-RSA-SHA256"),
+            ("X-Goog-Credential", &credential),
+            ("X-Goog-Date", &timestamp),
+            ("X-Goog-Expires", &expires_in.as_secs().to_string()),
+            ("X-Goog-SignedHeaders", "host"),
+        ]
+        .iter()
+        .map(|(name, value)| {
+            format!("{}={}", name, sign_encode(value, V4_QUERY))
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+            method, path, query, host
+        );
+        let digest = ring::digest::digest(
+            &ring::digest::SHA256,
+            canonical_request.as_bytes(),
+        );
+        let string_to_sign = format!(
+            "GOOG4-RSA-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(digest.as_ref())
+        );
+        let signature = sign(credentials, &string_to_sign)?;
+        Ok(format!(
+            "{}://{}{}?{}&X-Goog-Signature={}",
+            scheme, host, path, query, signature
+        ))
+    }
+
+    /// POST policy of uploads meeting conditions for expires_in from
+    /// now, see https://cloud.google.com/storage/docs/xml-api/post-object-forms
+    pub(crate) fn post_policy_at(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+        now: DateTime<Utc>,
+    ) -> BlobResult<PostForm> {
+        check_expiry(expires_in)?;
+        let (credentials, scheme, host) = self.signer()?;
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut fields = Vec::new();
+        if let Some(ref content_type) = conditions.content_type {
+            fields.push(("Content-Type".to_owned(), content_type.clone()));
+        }
+        fields.push((
+            "x-goog-algorithm".to_owned(),
+            "GOOG4-RSA-SHA256".to_owned(),
+        ));
+        fields.push((
+            "x-goog-credential".to_owned(),
+            format!(
+                "{}/{}/auto/storage/goog4_request",
+                credentials.client_email(),
+                &timestamp[..8]
+            ),
+        ));
+        fields.push(("x-goog-date".to_owned(), timestamp));
+        let policy = post_policy_document(
+            &self.name, conditions, &fields, now, expires_in,
+        );
+        let signature = sign(credentials, &policy)?;
+        fields.push(("policy".to_owned(), policy));
+        fields.push(("x-goog-signature".to_owned(), signature));
+        Ok(PostForm {
+            url: format!("{}://{}/{}", scheme, host, self.name),
+            fields,
+        })
+    }
+
+    pub async fn exists(project: &str, bucket: &str) -> BucketResult<bool> {
+        let mut buckets = GcpBuckets::new(project)?;
         buckets.exists(bucket).await
     }
+
+    /// Set how blobs are uploaded through resumable sessions
+    pub fn with_resumable_config(mut self, config: ResumableConfig) -> Self {
+        self.resumable = config;
+        self
+    }
+
+    /// Set how failed requests of this bucket and its blobs are retried
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Set how long requests of this bucket and its blobs may take,
+    /// TimeoutConfig::scope gives a single call its own
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        self.timeouts = config;
+        self
+    }
+
+    fn blob(&self, obj: cloud_storage::Object) -> GcpBlob {
+        let mut blob = GcpBlob::from_object(obj, self.user_project.clone());
+        blob.bucket = Some(self.clone());
+        blob.retry = self.retry;
+        blob.timeouts = self.timeouts;
+        blob
+    }
+
+    /// Start a resumable upload session for blob_name,
+    /// see ResumableUpload to send its content
+    pub async fn start_resumable_upload(
+        &self,
+        blob_name: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<ResumableUpload> {
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        self.start_resumable_upload_with_options(blob_name, &options)
+            .await
+    }
+
+    /// Start a resumable upload session for a blob written with the
+    /// headers and metadata of options, see start_resumable_upload
+    pub async fn start_resumable_upload_with_options(
+        &self,
+        blob_name: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<ResumableUpload> {
+        let url = format!(
+            "{}{}/{}/o?uploadType=resumable&name={}",
+            self.endpoint,
+            UPLOAD_PATH,
+            percent_encode(&self.name),
+            percent_encode(blob_name)
+        );
+        let (url, resource) = (&url, &ObjectResource::from(options));
+        // a session started by a failed attempt is left to expire
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let mut req = self.http.post(url).json(resource);
+                if let Some(ref content_type) = options.content_type {
+                    req = req.header("X-Upload-Content-Type", content_type);
+                }
+                send(self.credentials.as_deref(), &self.http, req).await
+            })
+            .await
+            .map_err(BlobError::WriteError)?;
+        let session_uri = resp
+            .headers()
+            .get(LOCATION)
+            .and_then(|uri| uri.to_str().ok())
+            .ok_or_else(|| {
+                BlobError::WriteError("Missing upload session".into())
+            })?;
+        Ok(ResumableUpload {
+            session_uri: session_uri.to_owned(),
+            offset: 0,
+            completed: None,
+            complete: false,
+            bucket: self.clone(),
+        })
+    }
+
+    /// Resume an upload session started by start_resumable_upload,
+    /// possibly in another process. Content has to be sent again
+    /// from ResumableUpload::offset
+    pub async fn resume_upload(
+        &self,
+        session_uri: &str,
+    ) -> BlobResult<ResumableUpload> {
+        let mut upload = ResumableUpload {
+            session_uri: session_uri.to_owned(),
+            offset: 0,
+            completed: None,
+            complete: false,
+            bucket: self.clone(),
+        };
+        upload.query().await?;
+        Ok(upload)
+    }
+
+    /// JSON API url of a blob's metadata
+    fn object_url(&self, blob_path: &str) -> String {
+        format!(
+            "{}{}/{}/o/{}",
+            self.endpoint,
+            BUCKETS_PATH,
+            percent_encode(&self.name),
+            percent_encode(blob_path)
+        )
+    }
+
+    /// Download bytes start to end of a blob, both included
+    async fn download_range(
+        &self,
+        blob_path: &str,
+        start: u64,
+        end: u64,
+    ) -> BlobResult<Vec<u8>> {
+        let url = format!("{}?alt=media", self.object_url(blob_path));
+        let url = &url;
+        let body = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let req = self
+                    .http
+                    .get(url)
+                    .header(RANGE, ByteRange::Bounded(start, end).to_string());
+                let resp =
+                    send(self.credentials.as_deref(), &self.http, req).await?;
+                Ok(resp.bytes().await?)
+            })
+            .await
+            .map_err(BlobError::GetError)?;
+        Ok(body.to_vec())
+    }
+
+    /// Upload content through a resumable session, chunks that fail
+    /// are sent again from where GCS stopped receiving them
+    pub async fn write_blob_resumable(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        content_type: Option<String>,
+    ) -> BlobResult<GcpBlob> {
+        let mut upload =
+            self.start_resumable_upload(blob_name, content_type).await?;
+        upload.upload(content).await
+    }
+
+    /// Upload content through a resumable session with the headers
+    /// and metadata of options, see write_blob_resumable
+    pub async fn write_blob_resumable_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<GcpBlob> {
+        let mut upload = self
+            .start_resumable_upload_with_options(blob_name, options)
+            .await?;
+        upload.upload(content).await
+    }
+}
+
+/// Resumable upload session of a single blob.
+/// The session URI can be persisted to resume the upload after a
+/// restart with GcpBucket::resume_upload, sessions expire after a week
+#[derive(Debug)]
+pub struct ResumableUpload {
+    session_uri: String,
+    /// Bytes GCS has persisted so far
+    offset: u64,
+    /// Blob created on completion, until upload hands it over
+    completed: Option<GcpBlob>,
+    complete: bool,
+    /// Bucket the upload was started from
+    bucket: GcpBucket,
+}
+
+impl ResumableUpload {
+    pub fn session_uri(&self) -> &str {
+        &self.session_uri
+    }
+
+    /// Number of bytes GCS has persisted, content passed
+    /// to upload must start from this offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Whether GCS has received all the content
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Send the rest of the content starting from offset,
+    /// returns the blob once GCS has received all of it
+    pub async fn upload(&mut self, content: BlobStream) -> BlobResult<GcpBlob> {
+        if let Some(blob) = self.completed.take() {
+            return Ok(blob);
+        }
+        if self.complete {
+            return Err(BlobError::WriteError(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                "Upload is already complete",
+            )));
+        }
+        let chunk_size = std::cmp::max(
+            self.bucket.resumable.chunk_size.div_ceil(CHUNK_ALIGNMENT)
+                * CHUNK_ALIGNMENT,
+            CHUNK_ALIGNMENT,
+        );
+        let mut chunks = Box::pin(chunked(content, chunk_size).peekable());
+        loop {
+            let chunk = match chunks.next().await {
+                Some(chunk) => chunk?,
+                None => Bytes::new(),
+            };
+            let last = chunks.as_mut().peek().await.is_none();
+            self.put_chunk(chunk, last).await?;
+            if let Some(blob) = self.completed.take() {
+                return Ok(blob);
+            }
+        }
+    }
+
+    /// Cancel the upload, discarding what GCS has received
+    pub async fn cancel(self) -> BlobResult<bool> {
+        let (session_uri, http) = (&self.session_uri, &self.bucket.http);
+        self.bucket
+            .timeouts
+            .send(&self.bucket.retry, || async move {
+                let resp = http
+                    .delete(session_uri)
+                    .header(CONTENT_LENGTH, 0)
+                    .send()
+                    .await?;
+                // GCS answers a cancelled session with 499
+                match resp.status().as_u16() {
+                    499 | 200 | 204 => Ok(true),
+                    _ => Err(error_details(resp).await),
+                }
+            })
+            .await
+            .map_err(BlobError::DeletionError)
+    }
+
+    /// Send chunk which starts at offset, attempts are made as the
+    /// bucket's RetryPolicy allows and every retry first asks GCS how
+    /// much of the chunk it persisted so only the rest is sent again
+    async fn put_chunk(&mut self, chunk: Bytes, last: bool) -> BlobResult<()> {
+        let start = self.offset;
+        let end = start + chunk.len() as u64;
+        let total = if last {
+            end.to_string()
+        } else {
+            String::from("*")
+        };
+        let offset = AtomicU64::new(start);
+        let retrying = AtomicBool::new(false);
+        let (session_uri, http) = (&self.session_uri, &self.bucket.http);
+        let (chunk, total, offset, retrying) =
+            (&chunk, &total, &offset, &retrying);
+        let resp = self
+            .bucket
+            .timeouts
+            .send(&self.bucket.retry, || async move {
+                if retrying.swap(true, Ordering::Relaxed) {
+                    let req = http
+                        .put(session_uri)
+                        .header(CONTENT_RANGE, "bytes */*")
+                        .header(CONTENT_LENGTH, 0);
+                    match session_status(req.send().await?).await? {
+                        Session::Persisted(persisted) => {
+                            offset.store(persisted, Ordering::Relaxed)
+                        }
+                        Session::Complete(obj) => return Ok(Some(obj)),
+                    }
+                }
+                loop {
+                    let sent = offset.load(Ordering::Relaxed);
+                    if sent >= end && !last {
+                        return Ok(None);
+                    }
+                    let body = chunk.slice((sent - start) as usize..);
+                    let range = if body.is_empty() {
+                        format!("bytes */{}", total)
+                    } else {
+                        format!(
+                            "bytes {}-{}/{}",
+                            sent,
+                            sent + body.len() as u64 - 1,
+                            total
+                        )
+                    };
+                    let req = http
+                        .put(session_uri)
+                        .header(CONTENT_RANGE, range)
+                        .header(CONTENT_LENGTH, body.len())
+                        .body(body);
+                    match session_status(req.send().await?).await? {
+                        Session::Persisted(persisted) if persisted > sent => {
+                            offset.store(persisted, Ordering::Relaxed)
+                        }
+                        Session::Persisted(_) => {
+                            return Err(ErrorDetails::from(
+                                "Upload made no progress",
+                            ))
+                        }
+                        Session::Complete(obj) => return Ok(Some(obj)),
+                    }
+                }
+            })
+            .await;
+        self.offset = offset.load(Ordering::Relaxed);
+        match resp {
+            Ok(Some(obj)) => {
+                self.complete(*obj);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    /// Ask GCS how much of the content it has persisted
+    async fn query(&mut self) -> BlobResult<()> {
+        let (session_uri, http) = (&self.session_uri, &self.bucket.http);
+        let status = self
+            .bucket
+            .timeouts
+            .send(&self.bucket.retry, || async move {
+                let req = http
+                    .put(session_uri)
+                    .header(CONTENT_RANGE, "bytes */*")
+                    .header(CONTENT_LENGTH, 0);
+                session_status(req.send().await?).await
+            })
+            .await;
+        match status {
+            Ok(Session::Persisted(persisted)) => {
+                self.offset = persisted;
+                Ok(())
+            }
+            Ok(Session::Complete(obj)) => {
+                self.complete(*obj);
+                Ok(())
+            }
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    /// Record the blob GCS created once it received all the content
+    fn complete(&mut self, obj: cloud_storage::Object) {
+        self.offset = obj.size;
+        self.complete = true;
+        self.completed = Some(self.bucket.blob(obj));
+    }
+}
+
+/// State of a resumable session as reported by GCS
+enum Session {
+    /// Bytes persisted so far, from a 308 Resume Incomplete response
+    Persisted(u64),
+    /// Object created once the upload is complete
+    Complete(Box<cloud_storage::Object>),
+}
+
+/// State of the session a response to an upload request reports
+async fn session_status(resp: Response) -> Result<Session, ErrorDetails> {
+    match resp.status() {
+        StatusCode::PERMANENT_REDIRECT => {
+            // Range is absent until GCS persists the first bytes
+            let persisted = resp
+                .headers()
+                .get(RANGE)
+                .and_then(persisted_bytes)
+                .unwrap_or(0);
+            Ok(Session::Persisted(persisted))
+        }
+        StatusCode::OK | StatusCode::CREATED => {
+            json(resp).await.map(|obj| Session::Complete(Box::new(obj)))
+        }
+        _ => Err(error_details(resp).await),
+    }
+}
+
+/// Number of bytes persisted from a Range header e.g "bytes=0-1048575"
+fn persisted_bytes(range: &HeaderValue) -> Option<u64> {
+    let range = range.to_str().ok()?.strip_prefix("bytes=")?;
+    let (_, end) = range.split_once('-')?;
+    end.parse::<u64>().ok().map(|end| end + 1)
+}
+
+/// Details of a failed JSON or upload API request, the upload id
+/// header is what GCS support asks for to trace a request
+async fn error_details(resp: Response) -> ErrorDetails {
+    let status = resp.status();
+    let request_id = resp
+        .headers()
+        .get("x-guploader-uploadid")
+        .and_then(|id| id.to_str().ok())
+        .map(str::to_owned);
+    let message = match resp.text().await {
+        Ok(text) if !text.is_empty() => text,
+        _ => status.to_string(),
+    };
+    let details = ErrorDetails::from_status(status.as_u16(), message);
+    match request_id {
+        Some(request_id) => details.with_request_id(request_id),
+        None => details,
+    }
+}
+
+impl From<reqwest::Error> for ErrorDetails {
+    fn from(error: reqwest::Error) -> Self {
+        reqwest_details(&error).with_source(error)
+    }
+}
+
+fn reqwest_details(error: &reqwest::Error) -> ErrorDetails {
+    match error.status() {
+        Some(status) => {
+            ErrorDetails::from_status(status.as_u16(), error.to_string())
+        }
+        None if error.is_timeout() => {
+            ErrorDetails::new(ErrorKind::Timeout, error.to_string())
+        }
+        None if error.is_connect() || error.is_request() || error.is_body() => {
+            ErrorDetails::new(ErrorKind::Connection, error.to_string())
+        }
+        None => ErrorDetails::new(ErrorKind::Other, error.to_string()),
+    }
+}
+
+/// Characters V4 signing leaves unencoded in query
+/// parameters, the unreserved ones of RFC 3986
+const V4_QUERY: &percent_encoding::AsciiSet =
+    &percent_encoding::NON_ALPHANUMERIC
+        .remove(b'-')
+        .remove(b'.')
+        .remove(b'_')
+        .remove(b'~');
+/// Characters V4 signing leaves unencoded in paths
+const V4_PATH: &percent_encoding::AsciiSet = &V4_QUERY.remove(b'/');
+
+/// Signature of message by the service account
+fn sign(credentials: &Credentials, message: &str) -> BlobResult<String> {
+    credentials.sign(message.as_bytes()).map_err(|e| {
+        BlobError::SignError(ErrorDetails::new(
+            ErrorKind::PermissionDenied,
+            e.to_string(),
+        ))
+    })
+}
+
+fn sign_encode(
+    value: &str,
+    set: &'static percent_encoding::AsciiSet,
+) -> String {
+    percent_encoding::utf8_percent_encode(value, set).to_string()
+}
+
+fn percent_encode(value: &str) -> String {
+    percent_encoding::utf8_percent_encode(
+        value,
+        percent_encoding::NON_ALPHANUMERIC,
+    )
+    .to_string()
 }
 
 #[async_trait]
@@ -248,170 +1355,303 @@ impl Bucket<GcpBlob> for GcpBucket {
         &self,
         marker: Option<String>,
     ) -> BucketResult<(Vec<GcpBlob>, Option<String>)> {
-        let all_objects = self
-            .client
-            .object()
-            .list(
-                self.name.as_str(),
-                ListRequest {
-                    page_token: marker,
-                    ..Default::default()
-                },
-            )
-            .await;
-        match all_objects {
-            Ok(object_list) => {
-                let mut ret: Vec<GcpBlob> = Vec::new();
-                let obj_stream = object_list
-                    .take(1)
-                    .collect::<Vec<CResult<ObjectList>>>()
-                    .await;
-                let bckt = obj_stream[0].as_ref().unwrap();
-                for obj in &bckt.items {
-                    ret.push(GcpBlob::new(
-                        Some(obj.name.clone()),
-                        Some(obj.etag.clone()),
-                        Some(obj.size as i64),
-                        None,
-                        obj.content_type.clone(),
-                        None,
-                        self.name.clone(),
-                        self.user_project.clone(),
-                    ))
-                }
-                Ok((ret, None))
-            }
-            Err(_) => {
-                Err(BucketError::ListError(String::from("could not list")))
+        let options = ListOptions {
+            marker,
+            ..Default::default()
+        };
+        let list = self.list_blobs_with_options(options).await?;
+        Ok((list.blobs, list.marker))
+    }
+
+    /// GCS start offsets are inclusive, a blob named exactly
+    /// start_after is dropped from the page
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<GcpBlob>> {
+        let mut query: Vec<(&str, String)> = Vec::new();
+        if let Some(ref prefix) = options.prefix {
+            query.push(("prefix", prefix.clone()));
+        }
+        if let Some(ref delimiter) = options.delimiter {
+            query.push(("delimiter", delimiter.clone()));
+        }
+        if let Some(ref start_after) = options.start_after {
+            query.push(("startOffset", start_after.clone()));
+        }
+        if let Some(max_results) = options.max_results {
+            query.push(("maxResults", max_results.to_string()));
+        }
+        if let Some(ref marker) = options.marker {
+            query.push(("pageToken", marker.clone()));
+        }
+        let url = format!(
+            "{}{}/{}/o",
+            self.endpoint,
+            BUCKETS_PATH,
+            percent_encode(&self.name)
+        );
+        let (url, query) = (&url, &query);
+        let page: ObjectList = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let req = self.http.get(url).query(query);
+                json(send(self.credentials.as_deref(), &self.http, req).await?)
+                    .await
+            })
+            .await
+            .map_err(BucketError::ListError)?;
+        let mut ret: Vec<GcpBlob> = Vec::new();
+        for obj in page.items {
+            if Some(&obj.name) == options.start_after.as_ref() {
+                continue;
             }
+            ret.push(self.blob(obj))
         }
+        Ok(BlobList {
+            blobs: ret,
+            prefixes: page.prefixes,
+            marker: page.next_page_token,
+        })
     }
     async fn get_blob(
         &self,
         blob_path: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<GcpBlob> {
+        let url = self.object_url(blob_path);
+        let url = &url;
         let resp = self
-            .client
-            .object()
-            .read(self.name.as_str(), blob_path)
+            .timeouts
+            .send(&self.retry, || async move {
+                let req = self.http.get(url);
+                json::<cloud_storage::Object>(
+                    send(self.credentials.as_deref(), &self.http, req).await?,
+                )
+                .await
+            })
             .await;
         match resp {
-            Ok(k) => Ok(GcpBlob::new(
-                Some(k.name.clone()),
-                Some(k.etag.clone()),
-                Some(k.size as i64),
-                None,
-                k.content_type,
-                content_range,
-                self.name.clone(),
-                self.user_project.clone(),
-            )),
-            Err(e) => Err(BlobError::GetError(String::from(format!("{}", e)))),
+            Ok(k) => {
+                let size = k.size;
+                let mut blob = self.blob(k);
+                if let Some(range) = range {
+                    let (start, end) = range.resolve(size)?;
+                    let body =
+                        self.download_range(blob_path, start, end).await?;
+                    blob.properties.size = Some(body.len() as u64);
+                    blob.body = Some(body);
+                    blob.content_range = Some(range.content_range(size)?);
+                }
+                Ok(blob)
+            }
+            Err(e) => Err(BlobError::GetError(e)),
         }
     }
 
+    /// get_blob only downloads content for ranged reads,
+    /// so this fetches the object's metadata alone
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<GcpBlob> {
+        self.get_blob(blob_path, None).await
+    }
+
+    /// Copies through rewrites, which GCS may split over several
+    /// requests for large objects. A content type replaces that of
+    /// the source
     async fn copy_blob(
         &self,
         blob_path: &str,
         blob_destination_path: &str,
         content_type: Option<String>,
     ) -> BlobResult<GcpBlob> {
-        let re = Regex::new(r"(?P<bucket>.*?)/(?P<blob_path>.*)").unwrap();
-        if let Some(captures) = re.captures(blob_destination_path) {
-            let bucket = captures.name("bucket").unwrap().as_str().to_owned();
-            let key = captures.name("blob_path").unwrap().as_str().to_owned();
-            let obj = self
-                .client
-                .object()
-                .read(self.name.as_str(), blob_path)
+        let (bucket, key) = split_destination(blob_destination_path)?;
+        let rewrite_url = format!(
+            "{}/rewriteTo/b/{}/o/{}",
+            self.object_url(blob_path),
+            percent_encode(bucket),
+            percent_encode(key)
+        );
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        let resource = ObjectResource::from(&options);
+        let mut token: Option<String> = None;
+        loop {
+            let url = match token {
+                Some(ref token) => format!(
+                    "{}?rewriteToken={}",
+                    rewrite_url,
+                    percent_encode(token)
+                ),
+                None => rewrite_url.clone(),
+            };
+            let (url, resource) = (&url, &resource);
+            let resp: RewriteResponse = self
+                .timeouts
+                .send(&self.retry, || async move {
+                    let req = self.http.post(url).json(resource);
+                    let resp =
+                        send(self.credentials.as_deref(), &self.http, req)
+                            .await?;
+                    json(resp).await
+                })
                 .await
-                .unwrap();
-            let resp = self.client.object().copy(&obj, &bucket, &key).await;
+                .map_err(BlobError::CopyError)?;
             match resp {
-                Ok(_) => Ok(GcpBlob::new(
-                    Some(key.to_string()),
-                    Some(obj.etag.clone()),
-                    Some(obj.size as i64),
-                    None,
-                    content_type,
-                    None,
-                    bucket.to_string(),
-                    self.user_project.clone(),
-                )),
-                Err(e) => {
-                    Err(BlobError::CopyError(String::from(format!("{}", e))))
+                RewriteResponse {
+                    resource: Some(copied),
+                    ..
+                } => return Ok(self.blob(copied)),
+                RewriteResponse {
+                    rewrite_token: Some(next),
+                    ..
+                } => token = Some(next),
+                _ => {
+                    return Err(BlobError::CopyError(ErrorDetails::from(
+                        "Rewrite returned neither the object nor a token",
+                    )))
                 }
             }
-        } else {
-            return Err(BlobError::CopyError(String::from(
-                r"Format blob_destination_path as {bucket}/{blob_path}",
-            )));
         }
     }
 
-    async fn write_blob(
+    /// Content up to the resumable threshold is uploaded in a single
+    /// multipart request, carrying the object resource along
+    async fn write_blob_with_options(
         &self,
         blob_name: &str,
         content: Option<Bytes>,
-        content_type: Option<String>,
+        options: &WriteOptions,
     ) -> BlobResult<GcpBlob> {
-        use bytes::Buf;
-        use std::io;
-
-        let mut file: Vec<u8> = Vec::new();
-        match content {
-            Some(x) => {
-                let mut reader = x.reader();
-                match io::copy(&mut reader, &mut file) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        return Err(BlobError::WriteError(String::from(
-                            format!("{}", e),
-                        )))
-                    }
+        let content = content.unwrap_or_default();
+        if content.len() > self.resumable.threshold {
+            let content = futures::stream::once(async { Ok(content) });
+            return self
+                .write_blob_resumable_with_options(
+                    blob_name,
+                    Box::pin(content),
+                    options,
+                )
+                .await;
+        }
+        let url = format!(
+            "{}{}/{}/o?uploadType=multipart&name={}",
+            self.endpoint,
+            UPLOAD_PATH,
+            percent_encode(&self.name),
+            percent_encode(blob_name)
+        );
+        let (boundary, body) = multipart_body(options, &content)?;
+        let content_type = format!("multipart/related; boundary={}", boundary);
+        let (body, content_type, url) = (&body, &content_type, &url);
+        let resp = self
+            .timeouts
+            .send(&self.retry, || async move {
+                let req = self
+                    .http
+                    .post(url)
+                    .header(CONTENT_TYPE, content_type)
+                    .body(body.clone());
+                json(send(self.credentials.as_deref(), &self.http, req).await?)
+                    .await
+            })
+            .await;
+        match resp {
+            Ok(obj) => Ok(self.blob(obj)),
+            Err(e) => Err(BlobError::WriteError(e)),
+        }
+    }
+
+    /// Content up to the resumable threshold is uploaded in a single
+    /// request, anything larger through write_blob_resumable
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        mut content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<GcpBlob> {
+        let mut buffer = BytesMut::new();
+        while buffer.len() <= self.resumable.threshold {
+            match content.next().await {
+                Some(chunk) => buffer.extend_from_slice(&chunk?),
+                None => {
+                    return self
+                        .write_blob_with_options(
+                            blob_name,
+                            Some(buffer.freeze()),
+                            options,
+                        )
+                        .await
                 }
             }
-            None => (),
-        }
-        let mime_type;
-        match content_type {
-            Some(c_type) => mime_type = c_type,
-            None => mime_type = "".to_owned(),
         }
+        let buffered = buffer.freeze();
+        let content =
+            futures::stream::once(async { Ok(buffered) }).chain(content);
+        self.write_blob_resumable_with_options(
+            blob_name,
+            Box::pin(content),
+            options,
+        )
+        .await
+    }
+
+    /// Patches the object, its current metadata is fetched first as
+    /// patches only remove the keys they name
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<GcpBlob> {
+        let current = self.stat_blob(blob_path).await?;
+        let patch = ObjectPatch::new(options, &current.properties.metadata);
+        let url = self.object_url(blob_path);
+        let (patch, url) = (&patch, &url);
         let resp = self
-            .client
-            .object()
-            .create(self.name.as_str(), file, blob_name, mime_type.as_str())
+            .timeouts
+            .send(&self.retry, || async move {
+                let req = self.http.patch(url).json(patch);
+                json(send(self.credentials.as_deref(), &self.http, req).await?)
+                    .await
+            })
             .await;
         match resp {
-            Ok(obj) => Ok(GcpBlob::new(
-                Some(obj.name.to_string()),
-                Some(obj.etag.clone()),
-                Some(obj.size as i64),
-                None,
-                obj.content_type,
-                None,
-                self.name.clone(),
-                self.user_project.clone(),
-            )),
-            Err(e) => {
-                Err(BlobError::WriteError(String::from(format!("{}", e))))
-            }
+            Ok(obj) => Ok(self.blob(obj)),
+            Err(e) => Err(BlobError::WriteError(e)),
         }
     }
 
     async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
+        let url = self.object_url(blob_path);
+        let (credentials, http, url) =
+            (self.credentials.as_deref(), &self.http, &url);
         let resp = self
-            .client
-            .object()
-            .delete(self.name.as_str(), blob_path)
+            .timeouts
+            .send(&self.retry, || async move {
+                send(credentials, http, http.delete(url)).await
+            })
             .await;
         match resp {
             Ok(_) => Ok(true),
-            Err(e) => {
-                Err(BlobError::DeletionError(String::from(format!("{}", e))))
-            }
+            Err(e) => Err(BlobError::DeletionError(e)),
         }
     }
+
+    async fn signed_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        self.signed_url_at(blob_path, method, expires_in, Utc::now())
+    }
+
+    async fn post_policy(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        self.post_policy_at(conditions, expires_in, Utc::now())
+    }
 }
@@ -0,0 +1,194 @@
+//! Service accounts GCS requests are made as, and the OAuth access
+//! tokens issued for them
+
+use crate::providers::hex;
+use crate::types::errors::{ErrorDetails, ErrorKind};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+pub(crate) type AuthResult<T> =
+    Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Token endpoint of key files that do not name one
+const TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.full_control";
+/// Tokens are refreshed this many seconds before they expire
+const EXPIRY_MARGIN: u64 = 60;
+/// Time allowed for a token request, requests waiting for the
+/// token are held until it completes
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    exp: u64,
+    iat: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Fields of a service account key file used to issue tokens
+#[derive(Deserialize)]
+struct ServiceAccount {
+    #[serde(rename = "type")]
+    account_type: String,
+    #[serde(default)]
+    project_id: String,
+    client_email: String,
+    private_key: String,
+    #[serde(default = "token_uri")]
+    token_uri: String,
+}
+
+fn token_uri() -> String {
+    TOKEN_URI.to_owned()
+}
+
+/// Service account a client authenticates as, with the access
+/// token last issued for it and the unix time it expires at
+pub(crate) struct Credentials {
+    account: ServiceAccount,
+    token: Mutex<Option<(String, u64)>>,
+}
+
+/// The private key is left out
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("client_email", &self.account.client_email)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    /// Parse the content of a service account key file
+    pub(crate) fn from_json(credentials_json: &str) -> AuthResult<Self> {
+        let account: ServiceAccount = serde_json::from_str(credentials_json)?;
+        if account.account_type != "service_account" {
+            return Err("Credentials are not of a service account".into());
+        }
+        Ok(Credentials {
+            account,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// Service account set in the environment: the key file at
+    /// SERVICE_ACCOUNT or GOOGLE_APPLICATION_CREDENTIALS, else the
+    /// content of SERVICE_ACCOUNT_JSON or
+    /// GOOGLE_APPLICATION_CREDENTIALS_JSON
+    pub(crate) fn from_env() -> AuthResult<Arc<Self>> {
+        let credentials_json = match std::env::var("SERVICE_ACCOUNT")
+            .or_else(|_| std::env::var("GOOGLE_APPLICATION_CREDENTIALS"))
+        {
+            Ok(path) => std::fs::read_to_string(path)?,
+            Err(_) => std::env::var("SERVICE_ACCOUNT_JSON")
+                .or_else(|_| {
+                    std::env::var("GOOGLE_APPLICATION_CREDENTIALS_JSON")
+                })
+                .map_err(|_| {
+                    "Set env variable GOOGLE_APPLICATION_CREDENTIALS \
+                     or SERVICE_ACCOUNT_JSON"
+                })?,
+        };
+        Ok(Arc::new(Self::from_json(&credentials_json)?))
+    }
+
+    /// Project the service account belongs to
+    pub(crate) fn project_id(&self) -> &str {
+        &self.account.project_id
+    }
+
+    pub(crate) fn client_email(&self) -> &str {
+        &self.account.client_email
+    }
+
+    /// Hex encoded RSA-SHA256 signature of message
+    /// made with the service account's private key
+    pub(crate) fn sign(&self, message: &[u8]) -> AuthResult<String> {
+        let pem: String = self
+            .account
+            .private_key
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = base64::decode(pem.trim())?;
+        let key = ring::signature::RsaKeyPair::from_pkcs8(&der)
+            .map_err(|e| format!("Invalid private key: {}", e))?;
+        let mut signature = vec![0; key.public_modulus_len()];
+        key.sign(
+            &ring::signature::RSA_PKCS1_SHA256,
+            &ring::rand::SystemRandom::new(),
+            message,
+            &mut signature,
+        )
+        .map_err(|_| "Could not sign with the private key")?;
+        Ok(hex(&signature))
+    }
+
+    /// Bearer token for the service account, cached until it expires,
+    /// requested through http from the key file's token_uri
+    pub(crate) async fn access_token(
+        &self,
+        http: &reqwest::Client,
+    ) -> Result<String, ErrorDetails> {
+        let mut token = self.token.lock().await;
+        let now = now();
+        if let Some((ref access_token, expiry)) = *token {
+            if expiry > now + EXPIRY_MARGIN {
+                return Ok(access_token.clone());
+            }
+        }
+        let claims = Claims {
+            iss: &self.account.client_email,
+            scope: SCOPE,
+            aud: &self.account.token_uri,
+            exp: now + 3600,
+            iat: now,
+        };
+        let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+        let assertion = jsonwebtoken::EncodingKey::from_rsa_pem(
+            self.account.private_key.as_bytes(),
+        )
+        .and_then(|key| jsonwebtoken::encode(&header, &claims, &key))
+        .map_err(|e| {
+            ErrorDetails::from_source(ErrorKind::PermissionDenied, e)
+        })?;
+        let resp = http
+            .post(&self.account.token_uri)
+            .timeout(TOKEN_TIMEOUT)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &assertion),
+            ])
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let message = match resp.text().await {
+                Ok(text) if !text.is_empty() => text,
+                _ => status.to_string(),
+            };
+            return Err(ErrorDetails::from_status(status.as_u16(), message));
+        }
+        let resp: TokenResponse = serde_json::from_slice(&resp.bytes().await?)
+            .map_err(|e| ErrorDetails::from_source(ErrorKind::Other, e))?;
+        *token = Some((resp.access_token.clone(), now + resp.expires_in));
+        Ok(resp.access_token)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
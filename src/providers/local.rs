@@ -0,0 +1,764 @@
+use crate::providers::apply_options;
+use crate::providers::list_page;
+use crate::providers::split_destination;
+use crate::providers::unsigned;
+use crate::types::blob::{
+    Blob, BlobProperties, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
+use crate::types::errors::{
+    BlobError, BlobResult, BucketError, BucketResult, ErrorDetails, ErrorKind,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+/// Directory under root holding blob metadata and in-flight writes
+const META_DIR: &str = ".waihona";
+/// Directory under META_DIR for in-flight writes, bucket names
+/// can't start with '.' so it never collides with a bucket's metadata
+const TMP_DIR: &str = ".tmp";
+/// Maximum number of blobs returned by a single list_blobs call
+const LIST_PAGE_SIZE: usize = 1000;
+/// Size of the chunks read_stream reads the file in
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+/// Suffix for temporary files, unique within this process
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Metadata persisted alongside every blob written through waihona
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocalMeta {
+    e_tag: Option<String>,
+    content_type: Option<String>,
+    cache_control: Option<String>,
+    content_encoding: Option<String>,
+    content_disposition: Option<String>,
+    content_language: Option<String>,
+    storage_class: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+impl LocalMeta {
+    /// Metadata of a blob written with options
+    fn new(e_tag: Option<String>, options: &WriteOptions) -> Self {
+        let options = options.clone();
+        LocalMeta {
+            e_tag,
+            content_type: options.content_type,
+            cache_control: options.cache_control,
+            content_encoding: options.content_encoding,
+            content_disposition: options.content_disposition,
+            content_language: options.content_language,
+            storage_class: options.storage_class,
+            metadata: options.metadata,
+        }
+    }
+
+    /// Options the blob was written with
+    fn options(self) -> WriteOptions {
+        WriteOptions {
+            content_type: self.content_type,
+            cache_control: self.cache_control,
+            content_encoding: self.content_encoding,
+            content_disposition: self.content_disposition,
+            content_language: self.content_language,
+            storage_class: self.storage_class,
+            metadata: self.metadata,
+        }
+    }
+}
+
+/// Reject keys that would escape the bucket directory or
+/// that cannot be represented as a file path
+fn validate_key(key: &str) -> BlobResult<()> {
+    let valid = !key.is_empty()
+        && !key.contains('\\')
+        && key
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..");
+    if valid {
+        Ok(())
+    } else {
+        Err(BlobError::GetError(ErrorDetails::new(
+            ErrorKind::InvalidArgument,
+            format!("Invalid blob path {}", key),
+        )))
+    }
+}
+
+/// Buckets are plain directories under root, hidden directories are
+/// reserved for waihona's own use
+fn validate_bucket_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains('/')
+        && !name.contains('\\')
+}
+
+/// Etag recorded when the blob was written, blobs placed in the
+/// bucket directory by other means get one derived from their
+/// modification time and size so they don't have to be read
+fn e_tag(meta: &LocalMeta, file: &std::fs::Metadata) -> Option<String> {
+    match meta.e_tag {
+        Some(ref e_tag) => Some(e_tag.clone()),
+        None => file
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|t| format!("\"{:x}-{:x}\"", t.as_nanos(), file.len())),
+    }
+}
+
+/// Whether path is an existing file
+async fn is_file(path: &Path) -> bool {
+    matches!(fs::metadata(path).await, Ok(m) if m.is_file())
+}
+
+/// Whether path is an existing directory
+async fn is_dir(path: &Path) -> bool {
+    matches!(fs::metadata(path).await, Ok(m) if m.is_dir())
+}
+
+/// Write content to path chunk by chunk,
+/// returns the etag of what was written
+async fn write_file(
+    path: &Path,
+    mut content: BlobStream,
+) -> BlobResult<String> {
+    let mut file = fs::File::create(path)
+        .await
+        .map_err(|e| BlobError::WriteError(e.into()))?;
+    let mut hash = md5::Context::new();
+    while let Some(chunk) = content.next().await {
+        let chunk = chunk?;
+        hash.consume(&chunk);
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| BlobError::WriteError(e.into()))?;
+    Ok(format!("\"{:x}\"", hash.compute()))
+}
+
+/// Remove empty directories from `dir` upwards, stopping at `stop`
+async fn prune_empty_dirs(mut dir: PathBuf, stop: &Path) {
+    while dir != stop && dir.starts_with(stop) {
+        if fs::remove_dir(&dir).await.is_err() {
+            break;
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+}
+
+/// Collection of buckets stored on the local filesystem.
+/// Every bucket is a directory under root and every blob is a file
+/// within the bucket directory
+#[derive(Debug, Clone)]
+pub struct LocalBuckets {
+    pub root: PathBuf,
+}
+
+impl LocalBuckets {
+    pub fn new(root: impl Into<PathBuf>) -> LocalBuckets {
+        LocalBuckets { root: root.into() }
+    }
+
+    fn bucket(&self, bucket_name: &str) -> LocalBucket {
+        LocalBucket {
+            name: bucket_name.to_owned(),
+            root: self.root.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalBucket {
+    pub name: String,
+    pub root: PathBuf,
+}
+
+impl LocalBucket {
+    pub async fn exists(
+        root: impl Into<PathBuf>,
+        bucket: &str,
+    ) -> BucketResult<bool> {
+        let mut buckets = LocalBuckets::new(root);
+        buckets.exists(bucket).await
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.root.join(&self.name)
+    }
+
+    fn meta_dir(&self) -> PathBuf {
+        self.root.join(META_DIR).join(&self.name)
+    }
+
+    fn blob_file(&self, key: &str) -> PathBuf {
+        self.dir().join(key)
+    }
+
+    fn meta_file(&self, key: &str) -> PathBuf {
+        self.meta_dir().join(format!("{}.json", key))
+    }
+
+    async fn read_meta(&self, key: &str) -> LocalMeta {
+        match fs::read(self.meta_file(key)).await {
+            Ok(raw) => serde_json::from_slice(&raw).unwrap_or_default(),
+            Err(_) => LocalMeta::default(),
+        }
+    }
+
+    async fn write_meta(&self, key: &str, meta: &LocalMeta) -> BlobResult<()> {
+        let path = self.meta_file(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| BlobError::WriteError(e.into()))?;
+        }
+        let raw = serde_json::to_vec(meta).map_err(|e| {
+            BlobError::WriteError(ErrorDetails::from_source(
+                ErrorKind::Other,
+                e,
+            ))
+        })?;
+        fs::write(path, raw)
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))
+    }
+
+    async fn blob(&self, key: &str) -> BlobResult<LocalBlob> {
+        let file_meta = match fs::metadata(self.blob_file(key)).await {
+            Ok(m) if m.is_file() => m,
+            _ => return Err(BlobError::NotFound),
+        };
+        let meta = self.read_meta(key).await;
+        let mut blob = LocalBlob::new(
+            key.to_owned(),
+            e_tag(&meta, &file_meta),
+            Some(file_meta.len() as i64),
+            None,
+            None,
+            self.name.clone(),
+            self.root.clone(),
+        );
+        let properties = &mut blob.properties;
+        apply_options(properties, meta.options());
+        properties.last_modified =
+            file_meta.modified().ok().map(DateTime::<Utc>::from);
+        properties.created =
+            file_meta.created().ok().map(DateTime::<Utc>::from);
+        Ok(blob)
+    }
+
+    /// All blob keys in the bucket sorted lexicographically,
+    /// the same order cloud providers list in
+    async fn keys(&self) -> std::io::Result<Vec<String>> {
+        let base = self.dir();
+        let mut keys = Vec::new();
+        let mut pending = vec![base.clone()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&base) {
+                    let parts: Vec<String> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect();
+                    keys.push(parts.join("/"));
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalBlob {
+    properties: BlobProperties,
+    content_range: Option<String>,
+    range: Option<(u64, u64)>,
+    root: PathBuf,
+}
+
+impl LocalBlob {
+    pub fn new(
+        key: String,
+        e_tag: Option<String>,
+        size: Option<i64>,
+        content_type: Option<String>,
+        content_range: Option<String>,
+        bucket: String,
+        root: PathBuf,
+    ) -> Self {
+        LocalBlob {
+            properties: BlobProperties {
+                e_tag,
+                size: size.map(|size| size as u64),
+                content_type,
+                ..BlobProperties::new(key, bucket)
+            },
+            content_range,
+            range: None,
+            root,
+        }
+    }
+
+    pub async fn get(
+        root: impl Into<PathBuf>,
+        bucket: &str,
+        blob_path: &str,
+        range: Option<ByteRange>,
+    ) -> BlobResult<Self> {
+        let mut buckets = LocalBuckets::new(root);
+        let bucket = buckets.open(bucket).await;
+        match bucket {
+            Ok(b) => b.get_blob(blob_path, range).await,
+            Err(e) => Err(BlobError::GetError(e.into())),
+        }
+    }
+
+    fn bucket(&self) -> LocalBucket {
+        LocalBucket {
+            name: self.properties.bucket.clone(),
+            root: self.root.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Blob for LocalBlob {
+    fn properties(&self) -> &BlobProperties {
+        &self.properties
+    }
+
+    fn content_range(&self) -> Option<&str> {
+        self.content_range.as_deref()
+    }
+
+    async fn delete(&self) -> BlobResult<bool> {
+        let resp = self.bucket().delete_blob(&self.properties.key).await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::DeletionError(e.into())),
+        }
+    }
+
+    async fn copy(
+        &self,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<bool> {
+        let resp = self
+            .bucket()
+            .copy_blob(
+                &self.properties.key,
+                blob_destination_path,
+                content_type,
+            )
+            .await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::CopyError(e.into())),
+        }
+    }
+
+    async fn write_with_options(
+        &self,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<bool> {
+        let resp = self
+            .bucket()
+            .write_blob_with_options(&self.properties.key, content, options)
+            .await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::WriteError(e.into())),
+        }
+    }
+
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()> {
+        let bucket = self.bucket();
+        let updated = bucket
+            .update_blob_metadata(&self.properties.key, options)
+            .await?;
+        self.properties = updated.properties;
+        Ok(())
+    }
+
+    async fn read(&mut self) -> BlobResult<Bytes> {
+        let path = self.bucket().blob_file(&self.properties.key);
+        let mut file = match fs::File::open(&path).await {
+            Ok(f) => f,
+            Err(e) => return Err(BlobError::ReadError(e.into())),
+        };
+        let mut content = Vec::new();
+        match self.range {
+            Some((start, end)) => {
+                file.seek(SeekFrom::Start(start))
+                    .await
+                    .map_err(|e| BlobError::ReadError(e.into()))?;
+                file.take(end - start + 1)
+                    .read_to_end(&mut content)
+                    .await
+                    .map_err(|e| BlobError::ReadError(e.into()))?;
+            }
+            None => {
+                file.read_to_end(&mut content)
+                    .await
+                    .map_err(|e| BlobError::ReadError(e.into()))?;
+            }
+        }
+        Ok(Bytes::from(content))
+    }
+
+    async fn read_stream(&mut self) -> BlobResult<BlobStream> {
+        let path = self.bucket().blob_file(&self.properties.key);
+        let mut file = match fs::File::open(&path).await {
+            Ok(f) => f,
+            Err(e) => return Err(BlobError::ReadError(e.into())),
+        };
+        let (start, len) = match self.range {
+            Some((start, end)) => (start, end - start + 1),
+            None => (0, u64::MAX),
+        };
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(|e| BlobError::ReadError(e.into()))?;
+        let stream =
+            ReaderStream::with_capacity(file.take(len), READ_CHUNK_SIZE)
+                .map_err(|e| BlobError::ReadError(e.into()));
+        Ok(Box::pin(stream))
+    }
+
+    async fn signed_url(
+        &self,
+        _method: HttpMethod,
+        _expires_in: Duration,
+    ) -> BlobResult<String> {
+        Err(unsigned("local"))
+    }
+}
+
+#[async_trait]
+impl Bucket<LocalBlob> for LocalBucket {
+    async fn list_blobs(
+        &self,
+        marker: Option<String>,
+    ) -> BucketResult<(Vec<LocalBlob>, Option<String>)> {
+        let options = ListOptions {
+            marker,
+            ..Default::default()
+        };
+        let list = self.list_blobs_with_options(options).await?;
+        Ok((list.blobs, list.marker))
+    }
+
+    /// Each LocalBlob does not have a content_range
+    /// as that can only be set via a get_blob request
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<LocalBlob>> {
+        let keys = match self.keys().await {
+            Ok(k) => k,
+            Err(e) => return Err(BucketError::ListError(e.into())),
+        };
+        let (keys, prefixes, marker) = list_page(
+            keys.iter().map(String::as_str),
+            &options,
+            LIST_PAGE_SIZE,
+        );
+        let mut blobs: Vec<LocalBlob> = Vec::new();
+        for key in keys {
+            match self.blob(key).await {
+                Ok(blob) => blobs.push(blob),
+                Err(e) => return Err(BucketError::ListError(e.into())),
+            }
+        }
+        Ok(BlobList {
+            blobs,
+            prefixes,
+            marker,
+        })
+    }
+
+    async fn get_blob(
+        &self,
+        blob_path: &str,
+        range: Option<ByteRange>,
+    ) -> BlobResult<LocalBlob> {
+        validate_key(blob_path)?;
+        let mut blob = self.blob(blob_path).await?;
+        if let Some(range) = range {
+            let size = blob.properties.size.unwrap_or_default();
+            let (start, end) = range.resolve(size)?;
+            blob.properties.size = Some(end - start + 1);
+            blob.content_range = Some(range.content_range(size)?);
+            blob.range = Some((start, end));
+        }
+        Ok(blob)
+    }
+
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<LocalBlob> {
+        validate_key(blob_path)?;
+        self.blob(blob_path).await
+    }
+
+    async fn copy_blob(
+        &self,
+        blob_path: &str,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<LocalBlob> {
+        let (bucket, key) = split_destination(blob_destination_path)?;
+        let (bucket, key) = (bucket.to_owned(), key.to_owned());
+        validate_key(blob_path)?;
+        validate_key(&key)?;
+        let destination = LocalBuckets::new(self.root.clone())
+            .open(&bucket)
+            .await
+            .map_err(|e| BlobError::CopyError(e.into()))?;
+        let source = self.blob_file(blob_path);
+        if !is_file(&source).await {
+            return Err(BlobError::NotFound);
+        }
+        let target = destination.blob_file(&key);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| BlobError::CopyError(e.into()))?;
+        }
+        fs::copy(&source, &target)
+            .await
+            .map_err(|e| BlobError::CopyError(e.into()))?;
+        let mut meta = self.read_meta(blob_path).await;
+        if content_type.is_some() {
+            meta.content_type = content_type;
+        }
+        destination.write_meta(&key, &meta).await?;
+        destination.blob(&key).await
+    }
+
+    async fn write_blob_with_options(
+        &self,
+        blob_name: &str,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<LocalBlob> {
+        let content = content.unwrap_or_default();
+        let stream = futures::stream::once(async { Ok(content) });
+        self.write_blob_stream_with_options(
+            blob_name,
+            Box::pin(stream),
+            options,
+        )
+        .await
+    }
+
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<LocalBlob> {
+        validate_key(blob_name)?;
+        if !is_dir(&self.dir()).await {
+            return Err(BlobError::WriteError(ErrorDetails::new(
+                ErrorKind::NotFound,
+                format!("Bucket {} does not exist", self.name),
+            )));
+        }
+        let target = self.blob_file(blob_name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| BlobError::WriteError(e.into()))?;
+        }
+        // write to a temporary file first so readers never observe
+        // a partially written blob
+        let tmp_dir = self.root.join(META_DIR).join(TMP_DIR);
+        fs::create_dir_all(&tmp_dir)
+            .await
+            .map_err(|e| BlobError::WriteError(e.into()))?;
+        let tmp = tmp_dir.join(format!(
+            "{}-{}",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let e_tag = match write_file(&tmp, content).await {
+            Ok(written) => written,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp).await;
+                return Err(e);
+            }
+        };
+        if let Err(e) = fs::rename(&tmp, &target).await {
+            let _ = fs::remove_file(&tmp).await;
+            return Err(BlobError::WriteError(e.into()));
+        }
+        let meta = LocalMeta::new(Some(e_tag), options);
+        self.write_meta(blob_name, &meta).await?;
+        self.blob(blob_name).await
+    }
+
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<LocalBlob> {
+        validate_key(blob_path)?;
+        if !is_file(&self.blob_file(blob_path)).await {
+            return Err(BlobError::NotFound);
+        }
+        let current = self.read_meta(blob_path).await;
+        let options = WriteOptions {
+            storage_class: current.storage_class,
+            ..options.clone()
+        };
+        let meta = LocalMeta::new(current.e_tag, &options);
+        self.write_meta(blob_path, &meta).await?;
+        self.blob(blob_path).await
+    }
+
+    async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
+        validate_key(blob_path)?;
+        let path = self.blob_file(blob_path);
+        match fs::remove_file(&path).await {
+            Ok(_) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(BlobError::NotFound)
+            }
+            Err(e) => return Err(BlobError::DeletionError(e.into())),
+        }
+        let meta = self.meta_file(blob_path);
+        let _ = fs::remove_file(&meta).await;
+        // directories only exist to hold blobs, drop them once empty
+        if let Some(parent) = path.parent() {
+            prune_empty_dirs(parent.to_path_buf(), &self.dir()).await;
+        }
+        if let Some(parent) = meta.parent() {
+            prune_empty_dirs(parent.to_path_buf(), &self.meta_dir()).await;
+        }
+        Ok(true)
+    }
+
+    async fn signed_url(
+        &self,
+        _blob_path: &str,
+        _method: HttpMethod,
+        _expires_in: Duration,
+    ) -> BlobResult<String> {
+        Err(unsigned("local"))
+    }
+
+    async fn post_policy(
+        &self,
+        _conditions: &PostConditions,
+        _expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        Err(unsigned("local"))
+    }
+}
+
+#[async_trait]
+impl Buckets<LocalBucket, LocalBlob> for LocalBuckets {
+    async fn list(&mut self) -> BucketResult<Vec<LocalBucket>> {
+        let mut buckets: Vec<LocalBucket> = Vec::new();
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(e) => e,
+            // no bucket was ever created
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(buckets)
+            }
+            Err(e) => return Err(BucketError::ListError(e.into())),
+        };
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| BucketError::ListError(e.into()))?
+        {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_dir = matches!(entry.file_type().await, Ok(t) if t.is_dir());
+            if is_dir && validate_bucket_name(&name) {
+                buckets.push(self.bucket(&name));
+            }
+        }
+        buckets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(buckets)
+    }
+
+    async fn open(&mut self, bucket_name: &str) -> BucketResult<LocalBucket> {
+        if self.exists(bucket_name).await? {
+            Ok(self.bucket(bucket_name))
+        } else {
+            Err(BucketError::NotFound)
+        }
+    }
+
+    async fn create(
+        &mut self,
+        bucket_name: &str,
+        _location: Option<String>,
+    ) -> BucketResult<LocalBucket> {
+        if !validate_bucket_name(bucket_name) {
+            return Err(BucketError::CreationError(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid bucket name {}", bucket_name),
+            )));
+        }
+        if let Err(e) = fs::create_dir_all(&self.root).await {
+            return Err(BucketError::CreationError(e.into()));
+        }
+        match fs::create_dir(self.root.join(bucket_name)).await {
+            Ok(_) => Ok(self.bucket(bucket_name)),
+            Err(e) => Err(BucketError::CreationError(e.into())),
+        }
+    }
+
+    async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        if self.exists(bucket_name).await? {
+            let bucket = self.bucket(bucket_name);
+            // like cloud buckets, only empty buckets can be deleted
+            match fs::remove_dir(bucket.dir()).await {
+                Ok(_) => {
+                    let _ = fs::remove_dir_all(bucket.meta_dir()).await;
+                    Ok(true)
+                }
+                Err(e) => Err(BucketError::DeletionError(e.into())),
+            }
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        Ok(validate_bucket_name(bucket_name)
+            && is_dir(&self.root.join(bucket_name)).await)
+    }
+}
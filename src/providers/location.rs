@@ -0,0 +1,342 @@
+//! Open buckets from storage urls such as the ones found in config files
+//!
+//! - `s3://bucket/prefix?region=us-east-2`
+//! - `gs://bucket/prefix?project=my-project`
+//! - `az://container/prefix?account=mystorageaccount`
+//! - `file:///srv/storage/bucket/prefix?root=/srv/storage`
+
+use crate::providers::any::{AnyBlob, AnyBucket, AnyBuckets, Provider};
+use crate::types::blob::{BlobStream, ByteRange, HttpMethod, WriteOptions};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
+use crate::types::errors::{
+    BlobResult, BucketError, BucketResult, ErrorDetails, ErrorKind,
+};
+use crate::types::timeout::TimeoutConfig;
+use async_trait::async_trait;
+use bytes::Bytes;
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+/// Storage url broken down into what is needed to open a bucket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageUrl {
+    pub provider: Provider,
+    /// region on aws, project name on gcp, storage account on azure
+    /// and root directory on local, see AnyBuckets::new
+    pub location: String,
+    pub bucket: String,
+    /// Prefix blob paths are relative to, empty or ending with "/"
+    pub prefix: String,
+}
+
+impl StorageUrl {
+    /// Parse a storage url, the location falls back to the
+    /// environment when left out of the url:
+    /// AWS_REGION or AWS_DEFAULT_REGION (else us-east-1) on s3,
+    /// GOOGLE_CLOUD_PROJECT on gs and AZURE_STORAGE_ACCOUNT on az.
+    /// On file urls the last directory is the bucket unless a
+    /// root is specified
+    pub fn parse(url: &str) -> BucketResult<Self> {
+        let invalid = |reason: &str| {
+            BucketError::OpenError(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid storage url {}: {}", url, reason),
+            ))
+        };
+        let parsed = Url::parse(url).map_err(|e| invalid(&e.to_string()))?;
+        let query: HashMap<String, String> =
+            parsed.query_pairs().into_owned().collect();
+        let (provider, location, path) = match parsed.scheme() {
+            #[cfg(feature = "aws")]
+            "s3" => (
+                Provider::Aws,
+                Self::option(
+                    &query,
+                    "region",
+                    &["AWS_REGION", "AWS_DEFAULT_REGION"],
+                )
+                .unwrap_or_else(|| "us-east-1".to_owned()),
+                Self::bucket_path(&parsed)
+                    .ok_or_else(|| invalid("missing bucket"))?,
+            ),
+            #[cfg(feature = "gcp")]
+            "gs" => (
+                Provider::Gcp,
+                Self::option(&query, "project", &["GOOGLE_CLOUD_PROJECT"])
+                    .unwrap_or_default(),
+                Self::bucket_path(&parsed)
+                    .ok_or_else(|| invalid("missing bucket"))?,
+            ),
+            #[cfg(feature = "azure")]
+            "az" => (
+                Provider::Azure,
+                Self::option(&query, "account", &["AZURE_STORAGE_ACCOUNT"])
+                    .ok_or_else(|| invalid("missing storage account"))?,
+                Self::bucket_path(&parsed)
+                    .ok_or_else(|| invalid("missing container"))?,
+            ),
+            #[cfg(feature = "local")]
+            "file" => {
+                let path = parsed
+                    .to_file_path()
+                    .map_err(|_| invalid("not a local path"))?;
+                let root = match query.get("root") {
+                    Some(root) => std::path::PathBuf::from(root),
+                    None => path
+                        .parent()
+                        .ok_or_else(|| invalid("missing bucket"))?
+                        .to_path_buf(),
+                };
+                let relative = path
+                    .strip_prefix(&root)
+                    .map_err(|_| invalid("path is not within root"))?;
+                let parts: Vec<String> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                (
+                    Provider::Local,
+                    root.to_string_lossy().into_owned(),
+                    parts.join("/"),
+                )
+            }
+            scheme => {
+                return Err(invalid(&format!("unsupported scheme {}", scheme)))
+            }
+        };
+        let (bucket, prefix) = match path.split_once('/') {
+            Some((bucket, prefix)) => (bucket.to_owned(), prefix),
+            None => (path.clone(), ""),
+        };
+        if bucket.is_empty() {
+            return Err(invalid("missing bucket"));
+        }
+        let prefix = prefix.trim_matches('/');
+        Ok(StorageUrl {
+            provider,
+            location,
+            bucket,
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", prefix)
+            },
+        })
+    }
+
+    /// Query parameter, else the first environment variable set
+    #[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+    fn option(
+        query: &HashMap<String, String>,
+        name: &str,
+        env: &[&str],
+    ) -> Option<String> {
+        query
+            .get(name)
+            .cloned()
+            .or_else(|| env.iter().find_map(|var| std::env::var(var).ok()))
+    }
+
+    /// "bucket/prefix" of a cloud storage url
+    #[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+    fn bucket_path(url: &Url) -> Option<String> {
+        let bucket = url.host_str().filter(|host| !host.is_empty())?;
+        let path = percent_decode_str(url.path()).decode_utf8_lossy();
+        Some(format!("{}{}", bucket, path))
+    }
+
+    /// Open the bucket this url points to
+    pub async fn open(&self) -> BucketResult<PrefixedBucket> {
+        let mut buckets = AnyBuckets::new(self.provider, &self.location)?;
+        let bucket = buckets.open(&self.bucket).await?;
+        Ok(PrefixedBucket::new(bucket, &self.prefix))
+    }
+}
+
+impl FromStr for StorageUrl {
+    type Err = BucketError;
+
+    fn from_str(url: &str) -> BucketResult<Self> {
+        StorageUrl::parse(url)
+    }
+}
+
+/// Parse a storage url, build the client for its provider and
+/// open the bucket scoped to the url's prefix
+pub async fn open_url(url: &str) -> BucketResult<PrefixedBucket> {
+    StorageUrl::parse(url)?.open().await
+}
+
+/// Bucket whose blob paths are all relative to a prefix,
+/// e.g get_blob("a.txt") on prefix "photos/" gets "photos/a.txt".
+/// Blobs returned keep their full path within the bucket
+#[derive(Clone)]
+pub struct PrefixedBucket {
+    bucket: AnyBucket,
+    prefix: String,
+}
+
+impl PrefixedBucket {
+    pub fn new(bucket: AnyBucket, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        PrefixedBucket {
+            bucket,
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", prefix)
+            },
+        }
+    }
+
+    /// Prefix blob paths are relative to
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Bucket without any prefix applied
+    pub fn bucket(&self) -> &AnyBucket {
+        &self.bucket
+    }
+
+    pub fn into_inner(self) -> AnyBucket {
+        self.bucket
+    }
+
+    /// Set how long requests of this bucket and its blobs may take
+    pub fn with_timeout_config(mut self, config: TimeoutConfig) -> Self {
+        self.bucket = self.bucket.with_timeout_config(config);
+        self
+    }
+
+    fn path(&self, blob_path: &str) -> String {
+        format!("{}{}", self.prefix, blob_path.trim_start_matches('/'))
+    }
+}
+
+#[async_trait]
+impl Bucket<AnyBlob> for PrefixedBucket {
+    /// Lists blobs under the prefix
+    async fn list_blobs(
+        &self,
+        marker: Option<String>,
+    ) -> BucketResult<(Vec<AnyBlob>, Option<String>)> {
+        let options = ListOptions {
+            marker,
+            ..Default::default()
+        };
+        let list = self.list_blobs_with_options(options).await?;
+        Ok((list.blobs, list.marker))
+    }
+
+    /// The prefix and start_after of options are relative to the
+    /// bucket's prefix, blobs and common prefixes returned keep
+    /// their full path within the bucket
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<AnyBlob>> {
+        let options = ListOptions {
+            prefix: Some(self.path(options.prefix.as_deref().unwrap_or(""))),
+            start_after: options.start_after.map(|after| self.path(&after)),
+            ..options
+        };
+        self.bucket.list_blobs_with_options(options).await
+    }
+
+    async fn get_blob(
+        &self,
+        blob_path: &str,
+        range: Option<ByteRange>,
+    ) -> BlobResult<AnyBlob> {
+        self.bucket.get_blob(&self.path(blob_path), range).await
+    }
+
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<AnyBlob> {
+        self.bucket.stat_blob(&self.path(blob_path)).await
+    }
+
+    /// blob_destination_path is not relative to the prefix
+    /// as it also names the destination bucket
+    async fn copy_blob(
+        &self,
+        blob_path: &str,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<AnyBlob> {
+        self.bucket
+            .copy_blob(
+                &self.path(blob_path),
+                blob_destination_path,
+                content_type,
+            )
+            .await
+    }
+
+    async fn write_blob_with_options(
+        &self,
+        blob_name: &str,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<AnyBlob> {
+        self.bucket
+            .write_blob_with_options(&self.path(blob_name), content, options)
+            .await
+    }
+
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<AnyBlob> {
+        let path = self.path(blob_name);
+        self.bucket
+            .write_blob_stream_with_options(&path, content, options)
+            .await
+    }
+
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<AnyBlob> {
+        self.bucket
+            .update_blob_metadata(&self.path(blob_path), options)
+            .await
+    }
+
+    async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
+        self.bucket.delete_blob(&self.path(blob_path)).await
+    }
+
+    async fn signed_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String> {
+        self.bucket
+            .signed_url(&self.path(blob_path), method, expires_in)
+            .await
+    }
+
+    /// Uploads are limited to blobs under the prefix
+    async fn post_policy(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        let conditions = PostConditions {
+            key_prefix: self.path(&conditions.key_prefix),
+            ..conditions.clone()
+        };
+        self.bucket.post_policy(&conditions, expires_in).await
+    }
+}
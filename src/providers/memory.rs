@@ -0,0 +1,504 @@
+use crate::providers::apply_options;
+use crate::providers::list_page;
+use crate::providers::split_destination;
+use crate::providers::unsigned;
+use crate::types::blob::{
+    Blob, BlobProperties, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::bucket::{
+    BlobList, Bucket, Buckets, ListOptions, PostConditions, PostForm,
+};
+use crate::types::errors::{
+    BlobError, BlobResult, BucketError, BucketResult, ErrorDetails, ErrorKind,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+/// Maximum number of blobs returned by a single list_blobs call
+const LIST_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone)]
+struct MemoryObject {
+    content: Bytes,
+    e_tag: String,
+    options: WriteOptions,
+    last_modified: DateTime<Utc>,
+    created: DateTime<Utc>,
+}
+
+impl MemoryObject {
+    fn new(content: Bytes, options: WriteOptions) -> Self {
+        MemoryObject {
+            e_tag: format!("\"{:x}\"", md5::compute(&content)),
+            content,
+            options,
+            last_modified: Utc::now(),
+            created: Utc::now(),
+        }
+    }
+}
+
+/// Buckets mapped to their blobs, blobs are kept sorted by key
+/// so listing returns them in the same order cloud providers do
+type StoreMap = HashMap<String, BTreeMap<String, MemoryObject>>;
+type Store = Arc<RwLock<StoreMap>>;
+
+/// Collection of buckets held in memory.
+/// Clones share the same underlying store, so buckets and blobs
+/// created through one handle are visible through every other
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBuckets {
+    store: Store,
+}
+
+impl MemoryBuckets {
+    pub fn new() -> MemoryBuckets {
+        MemoryBuckets::default()
+    }
+
+    fn bucket(&self, bucket_name: &str) -> MemoryBucket {
+        MemoryBucket {
+            name: bucket_name.to_owned(),
+            store: self.store.clone(),
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, StoreMap> {
+        self.store.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, StoreMap> {
+        self.store.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryBucket {
+    pub name: String,
+    store: Store,
+}
+
+impl MemoryBucket {
+    fn buckets(&self) -> MemoryBuckets {
+        MemoryBuckets {
+            store: self.store.clone(),
+        }
+    }
+
+    fn blob(&self, key: &str, object: &MemoryObject) -> MemoryBlob {
+        let mut blob = MemoryBlob::new(
+            key.to_owned(),
+            Some(object.e_tag.clone()),
+            Some(object.content.len() as i64),
+            None,
+            None,
+            None,
+            self.name.clone(),
+            self.store.clone(),
+        );
+        apply_options(&mut blob.properties, object.options.clone());
+        blob.properties.last_modified = Some(object.last_modified);
+        blob.properties.created = Some(object.created);
+        blob
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryBlob {
+    properties: BlobProperties,
+    body: Option<Bytes>,
+    content_range: Option<String>,
+    store: Store,
+}
+
+impl MemoryBlob {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        key: String,
+        e_tag: Option<String>,
+        size: Option<i64>,
+        body: Option<Bytes>,
+        content_type: Option<String>,
+        content_range: Option<String>,
+        bucket: String,
+        store: Store,
+    ) -> Self {
+        MemoryBlob {
+            properties: BlobProperties {
+                e_tag,
+                size: size.map(|size| size as u64),
+                content_type,
+                ..BlobProperties::new(key, bucket)
+            },
+            body,
+            content_range,
+            store,
+        }
+    }
+
+    fn bucket(&self) -> MemoryBucket {
+        MemoryBucket {
+            name: self.properties.bucket.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Blob for MemoryBlob {
+    fn properties(&self) -> &BlobProperties {
+        &self.properties
+    }
+
+    fn content_range(&self) -> Option<&str> {
+        self.content_range.as_deref()
+    }
+
+    async fn delete(&self) -> BlobResult<bool> {
+        let resp = self.bucket().delete_blob(&self.properties.key).await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::DeletionError(e.into())),
+        }
+    }
+
+    async fn copy(
+        &self,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<bool> {
+        let resp = self
+            .bucket()
+            .copy_blob(
+                &self.properties.key,
+                blob_destination_path,
+                content_type,
+            )
+            .await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::CopyError(e.into())),
+        }
+    }
+
+    async fn write_with_options(
+        &self,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<bool> {
+        let resp = self
+            .bucket()
+            .write_blob_with_options(&self.properties.key, content, options)
+            .await;
+        match resp {
+            Ok(_) => Ok(true),
+            Err(e) => Err(BlobError::WriteError(e.into())),
+        }
+    }
+
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()> {
+        let bucket = self.bucket();
+        let updated = bucket
+            .update_blob_metadata(&self.properties.key, options)
+            .await?;
+        self.properties = updated.properties;
+        Ok(())
+    }
+
+    /// Returns the content fetched by get_blob, blobs obtained
+    /// any other way read the current content of the blob
+    async fn read(&mut self) -> BlobResult<Bytes> {
+        if let Some(ref body) = self.body {
+            return Ok(body.clone());
+        }
+        let buckets = self.bucket().buckets();
+        let store = buckets.read();
+        store
+            .get(&self.properties.bucket)
+            .and_then(|blobs| blobs.get(&self.properties.key))
+            .map(|object| object.content.clone())
+            .ok_or_else(|| {
+                BlobError::ReadError(ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    "Blob no longer exists",
+                ))
+            })
+    }
+
+    /// Content is already in memory, so it comes as a single chunk
+    async fn read_stream(&mut self) -> BlobResult<BlobStream> {
+        let content = self.read().await?;
+        Ok(Box::pin(futures::stream::once(async { Ok(content) })))
+    }
+
+    async fn signed_url(
+        &self,
+        _method: HttpMethod,
+        _expires_in: Duration,
+    ) -> BlobResult<String> {
+        Err(unsigned("memory"))
+    }
+}
+
+#[async_trait]
+impl Bucket<MemoryBlob> for MemoryBucket {
+    async fn list_blobs(
+        &self,
+        marker: Option<String>,
+    ) -> BucketResult<(Vec<MemoryBlob>, Option<String>)> {
+        let options = ListOptions {
+            marker,
+            ..Default::default()
+        };
+        let list = self.list_blobs_with_options(options).await?;
+        Ok((list.blobs, list.marker))
+    }
+
+    /// Each MemoryBlob does not have a body/content_range
+    /// as those can only be gotten via a get_blob request
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<MemoryBlob>> {
+        let buckets = self.buckets();
+        let store = buckets.read();
+        let blobs = match store.get(&self.name) {
+            Some(blobs) => blobs,
+            None => return Err(BucketError::NotFound),
+        };
+        let keys = blobs.keys().map(String::as_str);
+        let (keys, prefixes, marker) =
+            list_page(keys, &options, LIST_PAGE_SIZE);
+        let blobs = keys
+            .into_iter()
+            .map(|key| self.blob(key, &blobs[key]))
+            .collect();
+        Ok(BlobList {
+            blobs,
+            prefixes,
+            marker,
+        })
+    }
+
+    async fn get_blob(
+        &self,
+        blob_path: &str,
+        range: Option<ByteRange>,
+    ) -> BlobResult<MemoryBlob> {
+        let buckets = self.buckets();
+        let store = buckets.read();
+        let object = store
+            .get(&self.name)
+            .and_then(|blobs| blobs.get(blob_path))
+            .ok_or(BlobError::NotFound)?;
+        let mut blob = self.blob(blob_path, object);
+        match range {
+            Some(range) => {
+                let size = object.content.len() as u64;
+                let (start, end) = range.resolve(size)?;
+                let body = object.content.slice(start as usize..=end as usize);
+                blob.properties.size = Some(body.len() as u64);
+                blob.body = Some(body);
+                blob.content_range = Some(range.content_range(size)?);
+            }
+            None => blob.body = Some(object.content.clone()),
+        }
+        Ok(blob)
+    }
+
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<MemoryBlob> {
+        let buckets = self.buckets();
+        let store = buckets.read();
+        store
+            .get(&self.name)
+            .and_then(|blobs| blobs.get(blob_path))
+            .map(|object| self.blob(blob_path, object))
+            .ok_or(BlobError::NotFound)
+    }
+
+    async fn copy_blob(
+        &self,
+        blob_path: &str,
+        blob_destination_path: &str,
+        content_type: Option<String>,
+    ) -> BlobResult<MemoryBlob> {
+        let (bucket, key) = split_destination(blob_destination_path)?;
+        let (bucket, key) = (bucket.to_owned(), key.to_owned());
+        let buckets = self.buckets();
+        let mut store = buckets.write();
+        let mut object = store
+            .get(&self.name)
+            .and_then(|blobs| blobs.get(blob_path))
+            .cloned()
+            .ok_or(BlobError::NotFound)?;
+        if content_type.is_some() {
+            object.options.content_type = content_type;
+        }
+        object.last_modified = Utc::now();
+        object.created = object.last_modified;
+        let destination = match store.get_mut(&bucket) {
+            Some(blobs) => blobs,
+            None => {
+                return Err(BlobError::CopyError(ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    format!("Bucket {} does not exist", bucket),
+                )))
+            }
+        };
+        let blob = buckets.bucket(&bucket).blob(&key, &object);
+        destination.insert(key, object);
+        Ok(blob)
+    }
+
+    async fn write_blob_with_options(
+        &self,
+        blob_name: &str,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<MemoryBlob> {
+        let buckets = self.buckets();
+        let mut store = buckets.write();
+        let blobs = match store.get_mut(&self.name) {
+            Some(blobs) => blobs,
+            None => {
+                return Err(BlobError::WriteError(ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    format!("Bucket {} does not exist", self.name),
+                )))
+            }
+        };
+        let object =
+            MemoryObject::new(content.unwrap_or_default(), options.clone());
+        let blob = self.blob(blob_name, &object);
+        blobs.insert(blob_name.to_owned(), object);
+        Ok(blob)
+    }
+
+    /// Chunks are gathered in memory, where the blob is stored anyway
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<MemoryBlob> {
+        let chunks: Vec<Bytes> = content.try_collect().await?;
+        self.write_blob_with_options(
+            blob_name,
+            Some(Bytes::from(chunks.concat())),
+            options,
+        )
+        .await
+    }
+
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
+    ) -> BlobResult<MemoryBlob> {
+        let buckets = self.buckets();
+        let mut store = buckets.write();
+        let object = store
+            .get_mut(&self.name)
+            .and_then(|blobs| blobs.get_mut(blob_path))
+            .ok_or(BlobError::NotFound)?;
+        object.options = WriteOptions {
+            storage_class: object.options.storage_class.take(),
+            ..options.clone()
+        };
+        object.last_modified = Utc::now();
+        Ok(self.blob(blob_path, object))
+    }
+
+    async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool> {
+        let buckets = self.buckets();
+        let mut store = buckets.write();
+        match store
+            .get_mut(&self.name)
+            .and_then(|blobs| blobs.remove(blob_path))
+        {
+            Some(_) => Ok(true),
+            None => Err(BlobError::NotFound),
+        }
+    }
+
+    async fn signed_url(
+        &self,
+        _blob_path: &str,
+        _method: HttpMethod,
+        _expires_in: Duration,
+    ) -> BlobResult<String> {
+        Err(unsigned("memory"))
+    }
+
+    async fn post_policy(
+        &self,
+        _conditions: &PostConditions,
+        _expires_in: Duration,
+    ) -> BlobResult<PostForm> {
+        Err(unsigned("memory"))
+    }
+}
+
+#[async_trait]
+impl Buckets<MemoryBucket, MemoryBlob> for MemoryBuckets {
+    async fn list(&mut self) -> BucketResult<Vec<MemoryBucket>> {
+        let mut names: Vec<String> = self.read().keys().cloned().collect();
+        names.sort();
+        Ok(names.iter().map(|name| self.bucket(name)).collect())
+    }
+
+    async fn open(&mut self, bucket_name: &str) -> BucketResult<MemoryBucket> {
+        if self.exists(bucket_name).await? {
+            Ok(self.bucket(bucket_name))
+        } else {
+            Err(BucketError::NotFound)
+        }
+    }
+
+    async fn create(
+        &mut self,
+        bucket_name: &str,
+        _location: Option<String>,
+    ) -> BucketResult<MemoryBucket> {
+        let mut store = self.write();
+        if store.contains_key(bucket_name) {
+            return Err(BucketError::CreationError(ErrorDetails::new(
+                ErrorKind::Conflict,
+                format!("Bucket {} already exists", bucket_name),
+            )));
+        }
+        store.insert(bucket_name.to_owned(), BTreeMap::new());
+        drop(store);
+        Ok(self.bucket(bucket_name))
+    }
+
+    async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        let mut store = self.write();
+        match store.get(bucket_name) {
+            // like cloud buckets, only empty buckets can be deleted
+            Some(blobs) if !blobs.is_empty() => {
+                Err(BucketError::DeletionError(ErrorDetails::new(
+                    ErrorKind::Conflict,
+                    format!("Bucket {} is not empty", bucket_name),
+                )))
+            }
+            Some(_) => {
+                store.remove(bucket_name);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool> {
+        Ok(self.read().contains_key(bucket_name))
+    }
+}
@@ -1,6 +1,231 @@
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+use crate::types::blob::BlobStream;
+#[cfg(any(feature = "aws", feature = "local", feature = "memory"))]
+use crate::types::blob::{BlobProperties, WriteOptions};
+#[cfg(any(feature = "local", feature = "memory"))]
+use crate::types::bucket::ListOptions;
+#[cfg(any(feature = "aws", feature = "gcp"))]
+use crate::types::bucket::PostConditions;
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local",
+    feature = "memory"
+))]
+use crate::types::errors::{BlobError, BlobResult, ErrorDetails, ErrorKind};
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+use bytes::{Bytes, BytesMut};
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+use futures::{Stream, StreamExt};
+
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local",
+    feature = "memory"
+))]
+pub mod any;
 #[cfg(feature = "aws")]
 pub mod aws;
 #[cfg(feature = "azure")]
 pub mod azure;
 #[cfg(feature = "gcp")]
 pub mod gcp;
+#[cfg(feature = "gcp")]
+mod gcp_auth;
+#[cfg(feature = "local")]
+pub mod local;
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local"
+))]
+pub mod location;
+#[cfg(feature = "memory")]
+pub mod memory;
+
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local",
+    feature = "memory"
+))]
+/// Split a copy destination "{bucket}/{blob_path}" into
+/// its bucket and blob path
+pub(crate) fn split_destination(path: &str) -> BlobResult<(&str, &str)> {
+    path.split_once('/').ok_or_else(|| {
+        BlobError::CopyError(ErrorDetails::new(
+            ErrorKind::InvalidArgument,
+            r"Format blob_destination_path as {bucket}/{blob_path}",
+        ))
+    })
+}
+
+#[cfg(any(feature = "aws", feature = "gcp"))]
+/// Fail on signed urls valid for longer than aws
+/// and gcp allow, a week
+pub(crate) fn check_expiry(expires_in: std::time::Duration) -> BlobResult<()> {
+    if expires_in.as_secs() > 7 * 24 * 3600 {
+        return Err(BlobError::SignError(ErrorDetails::new(
+            ErrorKind::InvalidArgument,
+            "Signed urls expire after a week at most",
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "aws", feature = "gcp"))]
+/// Base64 encoded policy document of a form upload to bucket meeting
+/// conditions, expiring expires_in after now. fields are conditions
+/// too, so that the form must send them as they are
+pub(crate) fn post_policy_document(
+    bucket: &str,
+    conditions: &PostConditions,
+    fields: &[(String, String)],
+    now: chrono::DateTime<chrono::Utc>,
+    expires_in: std::time::Duration,
+) -> String {
+    use serde_json::json;
+    let mut document = vec![
+        json!({ "bucket": bucket }),
+        json!(["starts-with", "$key", conditions.key_prefix]),
+    ];
+    if let Some(ref range) = conditions.content_length_range {
+        document.push(json!([
+            "content-length-range",
+            range.start(),
+            range.end()
+        ]));
+    }
+    for (name, value) in fields {
+        document.push(json!({ name: value }));
+    }
+    let expiration =
+        now + chrono::Duration::seconds(expires_in.as_secs() as i64);
+    let document = json!({
+        "conditions": document,
+        "expiration": expiration.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    });
+    base64::encode(document.to_string())
+}
+
+#[cfg(any(feature = "aws", feature = "gcp"))]
+/// Lowercase hex encoding of bytes
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(any(feature = "aws", feature = "local", feature = "memory"))]
+/// Set the properties of a blob written with options
+pub(crate) fn apply_options(
+    properties: &mut BlobProperties,
+    options: WriteOptions,
+) {
+    properties.content_type = options.content_type;
+    properties.cache_control = options.cache_control;
+    properties.content_encoding = options.content_encoding;
+    properties.content_disposition = options.content_disposition;
+    properties.content_language = options.content_language;
+    properties.storage_class = options.storage_class;
+    properties.metadata = options.metadata;
+}
+
+#[cfg(any(feature = "local", feature = "memory"))]
+/// Error of signing a url for a blob of a provider without urls
+pub(crate) fn unsigned(provider: &str) -> BlobError {
+    BlobError::SignError(ErrorDetails::new(
+        ErrorKind::Unsupported,
+        format!("{} blobs have no urls to sign", provider),
+    ))
+}
+
+#[cfg(any(feature = "local", feature = "memory"))]
+/// One page of sorted keys listed the way cloud providers list them.
+/// Returns the keys and common prefixes of the page along
+/// with the marker of the next page
+pub(crate) fn list_page<'a>(
+    keys: impl Iterator<Item = &'a str>,
+    options: &ListOptions,
+    page_size: usize,
+) -> (Vec<&'a str>, Vec<String>, Option<String>) {
+    let prefix = options.prefix.as_deref().unwrap_or_default();
+    let delimiter = options.delimiter.as_deref().filter(|d| !d.is_empty());
+    let after = std::cmp::max(
+        options.start_after.as_deref(),
+        options.marker.as_deref(),
+    );
+    let limit = options.max_results.unwrap_or(page_size).clamp(1, page_size);
+    let mut found = Vec::new();
+    let mut prefixes: Vec<String> = Vec::new();
+    let mut last = None;
+    for key in keys.filter(|key| key.starts_with(prefix)) {
+        if let Some(after) = after {
+            // a marker ending with the delimiter is a common prefix
+            // of the previous page, whose blobs were all grouped in it
+            let grouped = delimiter.is_some_and(|d| after.ends_with(d))
+                && key.starts_with(after);
+            if key <= after || grouped {
+                continue;
+            }
+        }
+        let common = delimiter.and_then(|d| {
+            key[prefix.len()..]
+                .find(d)
+                .map(|i| &key[..prefix.len() + i + d.len()])
+        });
+        if common.is_some() && prefixes.last().map(|p| p.as_str()) == common {
+            continue;
+        }
+        if found.len() + prefixes.len() == limit {
+            return (found, prefixes, last);
+        }
+        match common {
+            Some(common) => {
+                prefixes.push(common.to_owned());
+                last = Some(common.to_owned());
+            }
+            None => {
+                found.push(key);
+                last = Some(key.to_owned());
+            }
+        }
+    }
+    (found, prefixes, None)
+}
+
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+/// Regroup content into chunks of `size` bytes, only the last chunk
+/// may be smaller. Used to split uploads into parts or blocks
+pub(crate) fn chunked(
+    content: BlobStream,
+    size: usize,
+) -> impl Stream<Item = BlobResult<Bytes>> + Send {
+    let state = (content, BytesMut::new(), false);
+    futures::stream::try_unfold(
+        state,
+        move |(mut content, mut buffer, finished)| async move {
+            while !finished && buffer.len() < size {
+                match content.next().await {
+                    Some(chunk) => buffer.extend_from_slice(&chunk?),
+                    None => {
+                        let rest = buffer.split().freeze();
+                        if rest.is_empty() {
+                            return Ok(None);
+                        }
+                        return Ok(Some((rest, (content, buffer, true))));
+                    }
+                }
+            }
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            let len = std::cmp::min(size, buffer.len());
+            let chunk = buffer.split_to(len).freeze();
+            Ok(Some((chunk, (content, buffer, finished))))
+        },
+    )
+}
@@ -0,0 +1,66 @@
+#[test]
+fn test_provider_from_str() {
+    use crate::providers::any::Provider;
+    use crate::types::errors::ProviderError;
+    #[cfg(feature = "aws")]
+    assert_eq!("AWS".parse::<Provider>().unwrap(), Provider::Aws);
+    #[cfg(feature = "gcp")]
+    assert_eq!("gcs".parse::<Provider>().unwrap(), Provider::Gcp);
+    #[cfg(feature = "memory")]
+    assert_eq!("memory".parse::<Provider>().unwrap(), Provider::Memory);
+    assert!(matches!(
+        "dropbox".parse::<Provider>(),
+        Err(ProviderError::NotFound)
+    ));
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_any_provider_from_config() {
+    use crate::providers::any::{AnyBuckets, Provider};
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let provider: Provider = "memory".parse().unwrap();
+    let mut buckets = AnyBuckets::new(provider, "").unwrap();
+    assert_eq!(buckets.provider(), Provider::Memory);
+    let waihona = buckets.create("waihona", None).await.unwrap();
+    assert_eq!(waihona.provider(), Provider::Memory);
+    waihona
+        .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+        .await
+        .unwrap();
+    let (blobs, marker) = waihona.list_blobs(None).await.unwrap();
+    assert_eq!(blobs.len(), 1);
+    assert!(marker.is_none());
+    let mut blob = waihona.get_blob("hello.txt", None).await.unwrap();
+    assert!(blob.read().await.unwrap().eq(&Bytes::from("Hello world")));
+    assert!(blob.delete().await.unwrap());
+    assert!(buckets.delete("waihona").await.unwrap());
+}
+
+#[tokio::test]
+#[cfg(all(feature = "local", feature = "memory"))]
+async fn test_any_buckets_in_collection() {
+    use crate::providers;
+    use crate::providers::any::{AnyBucket, AnyBuckets};
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let root = tempfile::tempdir().unwrap();
+    let mut all: Vec<AnyBuckets> = vec![
+        providers::local::LocalBuckets::new(root.path()).into(),
+        providers::memory::MemoryBuckets::new().into(),
+    ];
+    let mut opened: Vec<AnyBucket> = Vec::new();
+    for buckets in all.iter_mut() {
+        opened.push(buckets.create("waihona", None).await.unwrap());
+    }
+    for bucket in opened.iter() {
+        let mut blob = bucket
+            .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+            .await
+            .unwrap();
+        assert!(blob.read().await.unwrap().eq(&Bytes::from("Hello world")));
+    }
+}
@@ -2,7 +2,9 @@
 #[cfg(feature = "aws")]
 async fn test_bucket_exists() {
     use crate::providers;
-    let resp = providers::aws::AwsBucket::exists("us-east-2", "waihona").await;
+    let resp = providers::aws::AwsBucket::exists("us-east-2", "waihona")
+        .await
+        .unwrap();
     assert!(resp);
 }
 
@@ -11,19 +13,429 @@ async fn test_bucket_exists() {
 async fn test_bucket_open() {
     use crate::providers;
     use crate::types::bucket::{Bucket, Buckets};
-    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2");
+    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
     let waihona = aws_buckets.open("waihona").await.unwrap();
     let blobs = waihona.list_blobs(None).await;
     println!("{:?}", blobs);
 }
 
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_bucket_open_missing() {
+    use crate::providers;
+    use crate::types::bucket::Buckets;
+    use crate::types::errors::BucketError;
+    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
+    let resp = aws_buckets.open("waihona-missing-bucket-4f1c").await;
+    assert!(matches!(resp, Err(BucketError::NotFound)));
+    assert!(!aws_buckets
+        .exists("waihona-missing-bucket-4f1c")
+        .await
+        .unwrap());
+}
+
+#[test]
+#[cfg(feature = "aws")]
+fn test_unknown_region() {
+    use crate::providers;
+    use crate::types::errors::ErrorKind;
+    match providers::aws::AwsBuckets::new("mars-central-1") {
+        Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidArgument),
+        Ok(_) => panic!("unknown region accepted"),
+    }
+}
+
+#[test]
+#[cfg(feature = "aws")]
+fn test_service_error_status() {
+    use crate::types::errors::{ErrorDetails, ErrorKind};
+    use rusoto_core::RusotoError;
+    use rusoto_s3::{CreateBucketError, GetObjectError, HeadObjectError};
+    let missing = ErrorDetails::from(RusotoError::Service(
+        HeadObjectError::NoSuchKey("missing".to_owned()),
+    ));
+    assert_eq!(missing.kind(), ErrorKind::NotFound);
+    assert_eq!(missing.status(), Some(404));
+    let archived = ErrorDetails::from(RusotoError::Service(
+        GetObjectError::InvalidObjectState("archived".to_owned()),
+    ));
+    assert_eq!(archived.kind(), ErrorKind::InvalidArgument);
+    assert_eq!(archived.status(), Some(403));
+    let owned = ErrorDetails::from(RusotoError::Service(
+        CreateBucketError::BucketAlreadyOwnedByYou("owned".to_owned()),
+    ));
+    assert_eq!(owned.kind(), ErrorKind::Conflict);
+    assert_eq!(owned.status(), Some(409));
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_custom_endpoint() {
+    use crate::providers;
+    use crate::types::bucket::Buckets;
+    use crate::types::errors::BucketError;
+    let (endpoint, request) = crate::tests::respond_once("404 Not Found").await;
+    let mut buckets = providers::aws::AwsBuckets::builder()
+        .region("minio")
+        .endpoint(endpoint)
+        .credentials("minioadmin", "minioadmin")
+        .build()
+        .unwrap();
+    match buckets.open("missing").await {
+        Err(BucketError::NotFound) => {}
+        resp => panic!("expected NotFound, got {:?}", resp.err()),
+    }
+    // buckets are addressed in the path
+    assert!(request.await.unwrap().starts_with("HEAD /missing "));
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_signed_url() {
+    use crate::providers;
+    use crate::types::blob::HttpMethod;
+    use crate::types::bucket::Bucket;
+    use crate::types::errors::ErrorKind;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint("http://localhost:9000")
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let bucket = buckets.bucket("waihona");
+    let expires_in = Duration::from_secs(900);
+    let now = Utc.with_ymd_and_hms(2021, 4, 12, 8, 30, 0).unwrap();
+    // expected signatures were made with python's hmac
+    for (method, signature) in [
+        (
+            HttpMethod::Get,
+            "4f0ee47ee19d7e6929c01a632a9045333d05e4fc94f3e59e19e49906b0bc89db",
+        ),
+        (
+            HttpMethod::Put,
+            "c5d90d768caa966d977fc2b414d51701fd507753705c28ef0bb0655a5e8167ab",
+        ),
+    ] {
+        let url = bucket
+            .signed_url_at("photos/my cat.jpg", method, expires_in, now)
+            .await
+            .unwrap();
+        let expected = concat!(
+            "http://localhost:9000/waihona/photos/my%20cat.jpg",
+            "?X-Amz-Algorithm=AWS4-HMAC-SHA256",
+            "&X-Amz-Credential=AKIDEXAMPLE%2F20210412%2Fus-east-1%2Fs3%2Faws4_request",
+            "&X-Amz-Date=20210412T083000Z&X-Amz-Expires=900",
+            "&X-Amz-SignedHeaders=host&X-Amz-Signature=",
+        );
+        assert_eq!(url, format!("{}{}", expected, signature));
+    }
+    let url = bucket
+        .signed_url("photos/my cat.jpg", HttpMethod::Get, expires_in)
+        .await
+        .unwrap();
+    assert!(
+        url.starts_with("http://localhost:9000/waihona/photos/my%20cat.jpg?")
+    );
+    let resp = bucket
+        .signed_url(
+            "cat.jpg",
+            HttpMethod::Get,
+            Duration::from_secs(8 * 24 * 3600),
+        )
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidArgument);
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_unknown_signing_region() {
+    use crate::providers::aws::{AwsBlob, AwsBucket};
+    use crate::types::blob::{Blob, HttpMethod};
+    use crate::types::bucket::Bucket;
+    use crate::types::errors::{BlobError, ErrorKind};
+    use std::time::Duration;
+    let expires_in = Duration::from_secs(900);
+    // a blob built on its own does not know its bucket's region
+    let blob = AwsBlob::new(
+        Some("cat.jpg".to_owned()),
+        None,
+        None,
+        None,
+        None,
+        None,
+        "waihona".to_owned(),
+    );
+    match blob.signed_url(HttpMethod::Get, expires_in).await {
+        Err(e @ BlobError::SignError(_)) => {
+            assert_eq!(e.kind(), ErrorKind::InvalidArgument)
+        }
+        resp => panic!("expected a sign error, got {:?}", resp),
+    }
+    let bucket = AwsBucket::new("waihona".to_owned(), None);
+    let resp = bucket
+        .signed_url("cat.jpg", HttpMethod::Get, expires_in)
+        .await;
+    assert!(matches!(resp, Err(BlobError::SignError(_))));
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_post_policy() {
+    use crate::providers;
+    use crate::types::bucket::{PostConditions, PostForm};
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint("http://localhost:9000/")
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let now = Utc.with_ymd_and_hms(2021, 4, 12, 8, 30, 0).unwrap();
+    let conditions = PostConditions {
+        key_prefix: "uploads/".to_owned(),
+        content_length_range: Some(1..=10 << 20),
+        content_type: Some("image/png".to_owned()),
+    };
+    let form = buckets
+        .bucket("waihona")
+        .post_policy_at(&conditions, Duration::from_secs(900), now)
+        .await
+        .unwrap();
+    let PostForm { url, fields } = form;
+    assert_eq!(url, "http://localhost:9000/waihona");
+    let names: Vec<_> = fields.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Content-Type",
+            "x-amz-algorithm",
+            "x-amz-credential",
+            "x-amz-date",
+            "policy",
+            "x-amz-signature"
+        ]
+    );
+    // expected signature was made with python's hmac
+    let policy = base64::decode(&fields[4].1).unwrap();
+    assert_eq!(
+        String::from_utf8(policy).unwrap(),
+        concat!(
+            r#"{"conditions":[{"bucket":"waihona"},"#,
+            r#"["starts-with","$key","uploads/"],"#,
+            r#"["content-length-range",1,10485760],"#,
+            r#"{"Content-Type":"image/png"},"#,
+            r#"{"x-amz-algorithm":"AWS4-HMAC-SHA256"},"#,
+            r#"{"x-amz-credential":"AKIDEXAMPLE/20210412/us-east-1/s3/aws4_request"},"#,
+            r#"{"x-amz-date":"20210412T083000Z"}],"#,
+            r#""expiration":"2021-04-12T08:45:00Z"}"#,
+        )
+    );
+    assert_eq!(
+        fields[5].1,
+        "55f9478465c6d6460e3d16fffffe9bf9c5a4bf0acc18d6f614ff8aa3996fc778"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_write_options() {
+    use crate::providers;
+    use crate::types::blob::{Blob, WriteOptions};
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    use bytes::Bytes;
+    let (endpoint, request) = crate::tests::receive_once("200 OK").await;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint(endpoint)
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let options = WriteOptions {
+        content_type: Some("image/png".to_owned()),
+        cache_control: Some("public, max-age=3600".to_owned()),
+        content_encoding: Some("gzip".to_owned()),
+        content_disposition: Some("inline".to_owned()),
+        content_language: Some("en-US".to_owned()),
+        storage_class: Some("STANDARD_IA".to_owned()),
+        metadata: std::iter::once(("team".to_owned(), "cdn".to_owned()))
+            .collect(),
+    };
+    // the blob is built from the write, not read back
+    let blob = bucket
+        .write_blob_with_options(
+            "photo.png",
+            Some(Bytes::from("Hello world")),
+            &options,
+        )
+        .await
+        .unwrap();
+    let properties = blob.properties();
+    assert_eq!(properties.size, Some(11));
+    assert_eq!(properties.cache_control, options.cache_control);
+    assert_eq!(properties.metadata, options.metadata);
+    let request = request.await.unwrap();
+    assert!(request.starts_with("PUT /waihona/photo.png "));
+    for header in [
+        "content-type: image/png",
+        "cache-control: public, max-age=3600",
+        "content-encoding: gzip",
+        "content-disposition: inline",
+        "content-language: en-US",
+        "x-amz-storage-class: STANDARD_IA",
+        "x-amz-meta-team: cdn",
+    ] {
+        assert!(request.contains(header), "{} missing", header);
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_update_blob_metadata() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::blob::{Blob, WriteOptions};
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let head = response(
+        "200 OK",
+        &[
+            ("content-length", "11"),
+            ("x-amz-storage-class", "STANDARD_IA"),
+            ("cache-control", "no-cache"),
+            ("x-amz-meta-owner", "media"),
+        ],
+        "",
+    );
+    let copied = response(
+        "200 OK",
+        &[],
+        "<CopyObjectResult><ETag>\"etag\"</ETag></CopyObjectResult>",
+    );
+    // the updated blob is built from the copy, not read back
+    let (endpoint, requests) =
+        crate::tests::receive_each(vec![head, copied]).await;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint(endpoint)
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let options = WriteOptions {
+        cache_control: Some("no-cache".to_owned()),
+        metadata: std::iter::once(("owner".to_owned(), "media".to_owned()))
+            .collect(),
+        ..Default::default()
+    };
+    let blob = bucket
+        .update_blob_metadata("photo.png", &options)
+        .await
+        .unwrap();
+    let properties = blob.properties();
+    assert_eq!(properties.metadata, options.metadata);
+    assert_eq!(properties.e_tag.as_deref(), Some("\"etag\""));
+    assert_eq!(properties.size, Some(11));
+    assert_eq!(properties.storage_class.as_deref(), Some("STANDARD_IA"));
+    let requests = requests.await.unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].starts_with("HEAD /waihona/photo.png "));
+    // the object is copied onto itself keeping its storage class
+    let copy = &requests[1];
+    assert!(copy.starts_with("PUT /waihona/photo.png "));
+    for header in [
+        "x-amz-copy-source: waihona/photo.png",
+        "x-amz-metadata-directive: REPLACE",
+        "x-amz-storage-class: STANDARD_IA",
+        "cache-control: no-cache",
+        "x-amz-meta-owner: media",
+    ] {
+        assert!(copy.contains(header), "{} missing", header);
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_copy_content_type() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let head = response(
+        "200 OK",
+        &[
+            ("content-length", "11"),
+            ("content-type", "image/png"),
+            ("cache-control", "no-cache"),
+            ("x-amz-meta-team", "cdn"),
+        ],
+        "",
+    );
+    let copied = response(
+        "200 OK",
+        &[],
+        "<CopyObjectResult><ETag>\"etag\"</ETag></CopyObjectResult>",
+    );
+    let (endpoint, requests) =
+        crate::tests::receive_each(vec![head.clone(), copied, head]).await;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint(endpoint)
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    bucket
+        .copy_blob(
+            "photo.png",
+            "backup/photo.png",
+            Some("text/plain".to_owned()),
+        )
+        .await
+        .unwrap();
+    let requests = requests.await.unwrap();
+    assert!(requests[0].starts_with("HEAD /waihona/photo.png "));
+    // the content type replaces the metadata, the rest is copied over
+    let copy = &requests[1];
+    assert!(copy.starts_with("PUT /backup/photo.png "));
+    for header in [
+        "x-amz-copy-source: waihona/photo.png",
+        "x-amz-metadata-directive: REPLACE",
+        "content-type: text/plain",
+        "cache-control: no-cache",
+        "x-amz-meta-team: cdn",
+    ] {
+        assert!(copy.contains(header), "{} missing", header);
+    }
+}
+
+#[test]
+#[cfg(feature = "aws")]
+fn test_explicit_credentials() {
+    use crate::providers;
+    use crate::types::errors::ErrorKind;
+    let builder = providers::aws::AwsBuckets::builder()
+        .credentials("AKIDEXAMPLE", "secret")
+        .session_token("token");
+    assert!(builder.clone().region("eu-west-2").build().is_ok());
+    match builder.region("mars-central-1").build() {
+        Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidArgument),
+        Ok(_) => panic!("unknown region accepted"),
+    }
+}
+
 #[tokio::test]
 #[cfg(feature = "aws")]
 async fn test_get_blob() {
     use crate::providers;
     use crate::types::blob::Blob;
     use crate::types::bucket::{Bucket, Buckets};
-    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2");
+    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
     let waihona = aws_buckets.open("waihona").await.unwrap();
     let mut blob = waihona.get_blob("reka-store.txt", None).await.unwrap();
 
@@ -50,3 +462,171 @@ async fn test_get_blob() {
     //    let res = blob.delete().await;
     //    println!("{:?}", res);
 }
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_stat_blob() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2").unwrap();
+    let waihona = aws_buckets.open("waihona").await.unwrap();
+    let mut blob = waihona.stat_blob("reka-store.txt").await.unwrap();
+    // the body is only downloaded now
+    let res = blob.read().await.unwrap();
+    assert!(!res.is_empty());
+    assert!(waihona.stat_blob("missing.txt").await.is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_chunked_parts() {
+    use crate::providers::chunked;
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    let content = futures::stream::iter(vec![
+        Ok(Bytes::from("Hel")),
+        Ok(Bytes::from("lo wor")),
+        Ok(Bytes::from("ld")),
+    ]);
+    let parts: Vec<Bytes> =
+        chunked(Box::pin(content), 4).try_collect().await.unwrap();
+    assert_eq!(parts, vec!["Hell", "o wo", "rld"]);
+    let empty = futures::stream::iter(vec![Ok(Bytes::new())]);
+    let parts: Vec<Bytes> =
+        chunked(Box::pin(empty), 4).try_collect().await.unwrap();
+    assert!(parts.is_empty());
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_write_blob_multipart() {
+    use crate::providers;
+    use crate::providers::aws::MultipartConfig;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut aws_buckets = providers::aws::AwsBuckets::new("us-east-2")
+        .unwrap()
+        .with_multipart_config(MultipartConfig {
+            part_size: 5 * 1024 * 1024,
+            concurrency: 2,
+            threshold: 5 * 1024 * 1024,
+        });
+    let waihona = aws_buckets.open("waihona").await.unwrap();
+    let content = Bytes::from(vec![7u8; 12 * 1024 * 1024]);
+    waihona
+        .write_blob("multipart.bin", Some(content.clone()), None)
+        .await
+        .unwrap();
+    let mut blob = waihona.get_blob("multipart.bin", None).await.unwrap();
+    assert_eq!(blob.read().await.unwrap(), content);
+    for upload in waihona.list_multipart_uploads().await.unwrap() {
+        if upload.key != "multipart.bin" {
+            continue;
+        }
+        waihona
+            .abort_multipart_upload(&upload.key, &upload.upload_id)
+            .await
+            .unwrap();
+    }
+    assert!(blob.delete().await.unwrap());
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_copy_source_encoding() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let copied = response(
+        "200 OK",
+        &[],
+        "<CopyObjectResult><ETag>\"etag\"</ETag></CopyObjectResult>",
+    );
+    let head = response("200 OK", &[("content-length", "11")], "");
+    let (endpoint, requests) =
+        crate::tests::receive_each(vec![copied, head]).await;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint(endpoint)
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    bucket
+        .copy_blob("my photos/a+b.png", "backup/a+b.png", None)
+        .await
+        .unwrap();
+    let requests = requests.await.unwrap();
+    assert!(requests[0]
+        .contains("x-amz-copy-source: waihona/my%20photos/a%2Bb.png"));
+}
+
+#[tokio::test]
+#[cfg(feature = "aws")]
+async fn test_abort_stale_multipart_uploads() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::errors::ErrorKind;
+    use crate::types::retry::RetryPolicy;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+    let upload = |key: &str, initiated: &str| {
+        format!(
+            "<Upload><Key>{}</Key><UploadId>{}-id</UploadId>\
+             <Initiated>{}</Initiated></Upload>",
+            key, key, initiated
+        )
+    };
+    let listed = response(
+        "200 OK",
+        &[],
+        &format!(
+            "<ListMultipartUploadsResult><Bucket>waihona</Bucket>\
+             <IsTruncated>false</IsTruncated>{}{}{}</ListMultipartUploadsResult>",
+            upload("stale.bin", "2021-04-12T08:30:00.000Z"),
+            upload("locked.bin", "2021-04-12T09:00:00.000Z"),
+            upload(
+                "fresh.bin",
+                &Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+            ),
+        ),
+    );
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        listed,
+        response("204 No Content", &[], ""),
+        response("403 Forbidden", &[], ""),
+    ])
+    .await;
+    let buckets = providers::aws::AwsBuckets::builder()
+        .endpoint(endpoint)
+        .credentials("AKIDEXAMPLE", "secret")
+        .build()
+        .unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let outcome = bucket
+        .abort_stale_multipart_uploads(Duration::from_secs(24 * 3600))
+        .await
+        .unwrap();
+    let aborted = outcome.aborted;
+    assert_eq!(aborted.len(), 1);
+    assert_eq!(aborted[0].key, "stale.bin");
+    assert_eq!(
+        aborted[0].initiated,
+        Some(Utc.with_ymd_and_hms(2021, 4, 12, 8, 30, 0).unwrap())
+    );
+    assert_eq!(outcome.failed.len(), 1);
+    let (failed, error) = &outcome.failed[0];
+    assert_eq!(failed.key, "locked.bin");
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    let requests = requests.await.unwrap();
+    assert!(requests[1]
+        .starts_with("DELETE /waihona/stale.bin?uploadId=stale.bin-id "));
+    assert!(requests[2]
+        .starts_with("DELETE /waihona/locked.bin?uploadId=locked.bin-id "));
+}
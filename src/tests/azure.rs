@@ -4,8 +4,8 @@ async fn test_container_listing() {
     use crate::providers;
     use crate::types::bucket::Buckets;
     let mut gcp_buckets =
-        providers::azure::AzureBuckets::new("waihona".to_owned());
-    let resp = gcp_buckets.list().await;
+        providers::azure::AzureBuckets::new("waihona".to_owned()).unwrap();
+    let resp = gcp_buckets.list().await.unwrap();
     println!("{:?}", resp);
 }
 
@@ -17,8 +17,9 @@ async fn test_container_exists() {
         "waihona",     // valid storage account
         "fake-bucket", // fake container name
     )
-    .await;
-    assert!(resp == false);
+    .await
+    .unwrap();
+    assert!(!resp);
 }
 
 #[tokio::test]
@@ -27,7 +28,7 @@ async fn test_container_list_blobs() {
     use crate::providers;
     use crate::types::bucket::{Bucket, Buckets};
     let mut azure_buckets =
-        providers::azure::AzureBuckets::new("waihona".to_owned());
+        providers::azure::AzureBuckets::new("waihona".to_owned()).unwrap();
     let resp = azure_buckets.open("waihona").await;
     let waihona = resp.unwrap();
     let blobs = waihona.list_blobs(None).await;
@@ -42,7 +43,7 @@ async fn test_container_read_blob() {
     use crate::types::bucket::{Bucket, Buckets};
     use bytes::Bytes;
     let mut azure_buckets =
-        providers::azure::AzureBuckets::new("waihona".to_owned());
+        providers::azure::AzureBuckets::new("waihona".to_owned()).unwrap();
     let resp = azure_buckets.open("waihona").await;
     let waihona = resp.unwrap();
 
@@ -55,8 +56,8 @@ async fn test_container_read_blob() {
     .await
     .unwrap();
     println!("Get Single Blob: {:?}", blob);
-    let read = blob.read().await.unwrap();
-//    println!("Reading Blob: {:?}", read);
+    let _read = blob.read().await.unwrap();
+    //    println!("Reading Blob: {:?}", read);
     let copied = waihona
         .copy_blob("CV latest.pdf", "waihona/copied.pdf", None)
         .await
@@ -89,7 +90,7 @@ async fn test_copy_blob_from_azure_to_gcp() {
     .await
     .unwrap();
     let mut gcp_buckets =
-        providers::gcp::GcpBuckets::new("psyched-myth-306812");
+        providers::gcp::GcpBuckets::new("psyched-myth-306812").unwrap();
     let resp = gcp_buckets.open("mythra").await;
     let mythra = resp.unwrap();
     let content: Option<Bytes> = Some(azure_blob.read().await.unwrap());
@@ -103,5 +104,605 @@ async fn test_copy_blob_from_azure_to_gcp() {
         .unwrap();
     let read = new.read().await.unwrap();
     let original_content = azure_blob.read().await.unwrap();
-    assert!(read.eq(&Bytes::from(original_content)));
+    assert!(read.eq(&original_content));
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_write_blob_blocks() {
+    use crate::providers;
+    use crate::providers::azure::BlockUploadConfig;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut azure_buckets =
+        providers::azure::AzureBuckets::new("waihona".to_owned())
+            .unwrap()
+            .with_block_upload_config(BlockUploadConfig {
+                block_size: 1024 * 1024,
+                concurrency: 2,
+                threshold: 1024 * 1024,
+            });
+    let waihona = azure_buckets.open("waihona").await.unwrap();
+    let content = Bytes::from(vec![7u8; 3 * 1024 * 1024 + 5]);
+    let mut blob = waihona
+        .write_blob("blocks.bin", Some(content.clone()), None)
+        .await
+        .unwrap();
+    assert_eq!(blob.read().await.unwrap(), content);
+    assert!(blob.delete().await.unwrap());
+}
+
+#[test]
+#[cfg(feature = "azure")]
+fn test_explicit_credentials() {
+    use crate::providers;
+    use crate::types::errors::ErrorKind;
+    let buckets = providers::azure::AzureBuckets::with_sas_token(
+        "account",
+        "?sv=2020-08-04&sig=signature",
+    )
+    .unwrap();
+    assert_eq!(buckets.storage_account, "account");
+    let connection_string = "DefaultEndpointsProtocol=https;\
+        AccountName=tenant;AccountKey=a2V5;EndpointSuffix=core.windows.net";
+    let buckets = providers::azure::AzureBuckets::with_connection_string(
+        connection_string,
+    )
+    .unwrap();
+    assert_eq!(buckets.storage_account, "tenant");
+    match providers::azure::AzureBuckets::with_connection_string("garbage") {
+        Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidArgument),
+        Ok(_) => panic!("invalid connection string accepted"),
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_emulator_endpoint() {
+    use crate::providers;
+    use crate::types::bucket::Buckets;
+    use crate::types::errors::{BucketError, ErrorKind};
+    let (endpoint, request) = crate::tests::respond_once("404 Not Found").await;
+    let mut buckets =
+        providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    assert_eq!(buckets.storage_account, "devstoreaccount1");
+    match buckets.open("missing").await {
+        Err(BucketError::NotFound) => {}
+        resp => panic!("expected NotFound, got {:?}", resp.err()),
+    }
+    // the account is addressed in the path
+    assert!(request
+        .await
+        .unwrap()
+        .starts_with("HEAD /devstoreaccount1/missing?"));
+    match providers::azure::AzureBuckets::emulator("not a url") {
+        Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidArgument),
+        Ok(_) => panic!("invalid endpoint accepted"),
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_signed_url() {
+    use crate::providers;
+    use crate::types::blob::HttpMethod;
+    use crate::types::bucket::Bucket;
+    use crate::types::errors::ErrorKind;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+    let buckets =
+        providers::azure::AzureBuckets::emulator("http://127.0.0.1:10000")
+            .unwrap();
+    let bucket = buckets.bucket("waihona");
+    let expiry = Utc.with_ymd_and_hms(2021, 4, 12, 9, 30, 0).unwrap();
+    // expected urls were signed with openssl
+    let url = bucket
+        .sas_url("photos/my cat.jpg", HttpMethod::Get, expiry)
+        .unwrap();
+    assert_eq!(
+        url,
+        "http://127.0.0.1:10000/devstoreaccount1/waihona/photos/my%20cat.jpg\
+         ?sv=2020-06-12&sp=r&sr=b&se=2021-04-12T09%3A30%3A00Z\
+         &sig=x9smtN%2F%2B8dOiWvgqQPFRNrIkBJ7qIO6roFitABdniLU%3D"
+    );
+    let url = bucket
+        .sas_url("photos/my cat.jpg", HttpMethod::Put, expiry)
+        .unwrap();
+    assert_eq!(
+        url,
+        "http://127.0.0.1:10000/devstoreaccount1/waihona/photos/my%20cat.jpg\
+         ?sv=2020-06-12&sp=cw&sr=b&se=2021-04-12T09%3A30%3A00Z\
+         &sig=Z7w3rAbHnStn8aXeQoNglXwfbR0NtyG6EqpPFJ6K%2FfI%3D"
+    );
+    // sas tokens cannot sign other urls
+    let buckets =
+        providers::azure::AzureBuckets::with_sas_token("account", "sv=token")
+            .unwrap();
+    let resp = buckets
+        .bucket("waihona")
+        .signed_url("cat.jpg", HttpMethod::Get, Duration::from_secs(900))
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Unsupported);
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_post_policy() {
+    use crate::providers;
+    use crate::types::bucket::{Bucket, PostConditions};
+    use crate::types::errors::ErrorKind;
+    use std::time::Duration;
+    let buckets =
+        providers::azure::AzureBuckets::emulator("http://127.0.0.1:10000")
+            .unwrap();
+    let resp = buckets
+        .bucket("waihona")
+        .post_policy(&PostConditions::default(), Duration::from_secs(900))
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Unsupported);
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_write_options() {
+    use crate::providers;
+    use crate::types::blob::WriteOptions;
+    use crate::types::bucket::Bucket;
+    use crate::types::errors::ErrorKind;
+    use crate::types::retry::RetryPolicy;
+    use bytes::Bytes;
+    let (endpoint, request) = crate::tests::receive_once("201 Created").await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let options = WriteOptions {
+        content_type: Some("image/png".to_owned()),
+        cache_control: Some("public, max-age=3600".to_owned()),
+        content_encoding: Some("gzip".to_owned()),
+        content_disposition: Some("inline".to_owned()),
+        content_language: Some("en-US".to_owned()),
+        storage_class: Some("Cool".to_owned()),
+        metadata: std::iter::once(("team".to_owned(), "cdn".to_owned()))
+            .collect(),
+    };
+    // the blob is fetched after it is written, which the server refuses
+    let _ = bucket
+        .write_blob_with_options(
+            "photo.png",
+            Some(Bytes::from("Hello world")),
+            &options,
+        )
+        .await;
+    let request = request.await.unwrap();
+    assert!(request.starts_with("PUT /devstoreaccount1/waihona/photo.png "));
+    for header in [
+        "x-ms-blob-type: BlockBlob",
+        "x-ms-blob-content-type: image/png",
+        "x-ms-blob-cache-control: public, max-age=3600",
+        "x-ms-blob-content-encoding: gzip",
+        "x-ms-blob-content-disposition: inline",
+        "x-ms-blob-content-language: en-US",
+        "x-ms-access-tier: Cool",
+        "x-ms-meta-team: cdn",
+    ] {
+        assert!(request.contains(header), "{} missing", header);
+    }
+    assert!(request.ends_with("\n\nHello world"));
+    // azure metadata keys are identifiers
+    let options = WriteOptions {
+        metadata: std::iter::once((
+            "not-an-identifier".to_owned(),
+            String::new(),
+        ))
+        .collect(),
+        ..Default::default()
+    };
+    let resp = bucket
+        .write_blob_with_options("photo.png", None, &options)
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::InvalidArgument);
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_update_blob_metadata() {
+    use crate::providers;
+    use crate::tests::azure::properties;
+    use crate::tests::response;
+    use crate::types::blob::{Blob, WriteOptions};
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let properties = properties();
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        properties.clone(),
+        response("200 OK", &[], ""),
+        response("200 OK", &[], ""),
+        properties,
+    ])
+    .await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let options = WriteOptions {
+        cache_control: Some("no-cache".to_owned()),
+        metadata: std::iter::once(("owner".to_owned(), "media".to_owned()))
+            .collect(),
+        ..Default::default()
+    };
+    let blob = bucket
+        .update_blob_metadata("photo.png", &options)
+        .await
+        .unwrap();
+    assert_eq!(blob.properties().metadata, options.metadata);
+    let requests = requests.await.unwrap();
+    assert!(requests[0].starts_with("HEAD /devstoreaccount1/waihona/photo.png"));
+    let set_properties = &requests[1];
+    assert!(set_properties.starts_with(
+        "PUT /devstoreaccount1/waihona/photo.png?comp=properties "
+    ));
+    assert!(set_properties.contains("x-ms-blob-cache-control: no-cache"));
+    // the content hash would be cleared if it was not set again
+    assert!(set_properties
+        .contains("x-ms-blob-content-md5: XrY7u+Ae7tCTyyK7j1rNww=="));
+    assert!(!set_properties.contains("x-ms-meta-"));
+    let set_metadata = &requests[2];
+    assert!(set_metadata
+        .starts_with("PUT /devstoreaccount1/waihona/photo.png?comp=metadata "));
+    assert!(set_metadata.contains("x-ms-meta-owner: media"));
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_block_retry() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::errors::ErrorKind;
+    use crate::types::retry::RetryPolicy;
+    use bytes::Bytes;
+    use std::time::Duration;
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        response("500 Internal Server Error", &[], ""),
+        response("403 Forbidden", &[], ""),
+    ])
+    .await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets.bucket("waihona").with_retry_policy(RetryPolicy {
+        base_delay: Duration::from_millis(1),
+        ..Default::default()
+    });
+    let content = futures::stream::once(async { Ok(Bytes::from("Hello")) });
+    let resp = bucket
+        .write_blob_blocks("blocks.bin", Box::pin(content), None)
+        .await;
+    // blocks go through the retry policy, which gives up on a 403
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    let requests = requests.await.unwrap();
+    assert!(requests
+        .iter()
+        .all(|request| request.contains("comp=block")));
+}
+
+/// Response to a Get Blob Properties request
+#[cfg(feature = "azure")]
+fn properties() -> String {
+    crate::tests::response(
+        "200 OK",
+        &[
+            ("content-length", "11"),
+            ("content-md5", "XrY7u+Ae7tCTyyK7j1rNww=="),
+            ("content-type", "image/png"),
+            ("cache-control", "no-cache"),
+            ("etag", "\"0x8D9\""),
+            ("last-modified", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-creation-time", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-blob-type", "BlockBlob"),
+            ("x-ms-lease-status", "unlocked"),
+            ("x-ms-lease-state", "available"),
+            ("x-ms-server-encrypted", "true"),
+            ("x-ms-request-id", "9c4e9c1a-201e-0004-2f5d-56f2d3000000"),
+            ("date", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-meta-owner", "media"),
+        ],
+        "",
+    )
+}
+
+/// Response to a synchronous Copy Blob From URL request
+#[cfg(feature = "azure")]
+fn copied() -> String {
+    crate::tests::response(
+        "202 Accepted",
+        &[
+            ("etag", "\"0x8D9\""),
+            ("last-modified", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("server", "Azurite"),
+            ("x-ms-request-id", "9c4e9c1a-201e-0004-2f5d-56f2d3000000"),
+            ("x-ms-version", "2019-12-12"),
+            ("x-ms-copy-id", "2a4e5f6b-201e-0004-2f5d-56f2d3000000"),
+            ("x-ms-copy-status", "success"),
+            ("date", "Tue, 01 Jun 2021 00:00:00 GMT"),
+        ],
+        "",
+    )
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_copy_content_type() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        copied(),
+        properties(),
+        response("200 OK", &[], ""),
+        properties(),
+    ])
+    .await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    bucket
+        .copy_blob(
+            "photo.png",
+            "waihona/copy.png",
+            Some("text/plain".to_owned()),
+        )
+        .await
+        .unwrap();
+    let requests = requests.await.unwrap();
+    assert!(requests[0].starts_with("PUT /devstoreaccount1/waihona/copy.png "));
+    // the copy's other properties and hash are sent along
+    let set_properties = &requests[2];
+    assert!(set_properties.starts_with(
+        "PUT /devstoreaccount1/waihona/copy.png?comp=properties "
+    ));
+    for header in [
+        "x-ms-blob-content-type: text/plain",
+        "x-ms-blob-cache-control: no-cache",
+        "x-ms-blob-content-md5: XrY7u+Ae7tCTyyK7j1rNww==",
+    ] {
+        assert!(set_properties.contains(header), "{} missing", header);
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_copy_other_container() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let (endpoint, requests) =
+        crate::tests::receive_each(vec![copied(), properties()]).await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let blob = bucket
+        .copy_blob("photo.png", "archive/copy.png", None)
+        .await
+        .unwrap();
+    assert_eq!(blob.properties().bucket, "archive");
+    assert_eq!(blob.properties().key, "copy.png");
+    let requests = requests.await.unwrap();
+    assert!(requests[0].starts_with("PUT /devstoreaccount1/archive/copy.png "));
+    assert!(requests[0].contains("/devstoreaccount1/waihona/photo.png"));
+    assert!(requests[1].starts_with("HEAD /devstoreaccount1/archive/copy.png "));
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_container_list_pages() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::bucket::Buckets;
+    let page = |names: &[&str], marker: &str| {
+        let containers: String = names
+            .iter()
+            .map(|name| {
+                format!(
+                    "<Container><Name>{}</Name><Properties>\
+                     <Last-Modified>Tue, 01 Jun 2021 00:00:00 GMT\
+                     </Last-Modified><Etag>\"0x8D9\"</Etag>\
+                     <LeaseStatus>unlocked</LeaseStatus>\
+                     <LeaseState>available</LeaseState>\
+                     <HasImmutabilityPolicy>false</HasImmutabilityPolicy>\
+                     <HasLegalHold>false</HasLegalHold>\
+                     </Properties></Container>",
+                    name
+                )
+            })
+            .collect();
+        response(
+            "200 OK",
+            &[
+                ("content-type", "application/xml"),
+                ("x-ms-request-id", "9c4e9c1a-201e-0004-2f5d-56f2d3000000"),
+                ("x-ms-version", "2019-12-12"),
+                ("date", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ],
+            &format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+                 <EnumerationResults><Containers>{}</Containers>\
+                 <NextMarker>{}</NextMarker></EnumerationResults>",
+                containers, marker
+            ),
+        )
+    };
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        page(&["first", "second"], "second-page"),
+        page(&["third"], ""),
+    ])
+    .await;
+    let mut buckets =
+        providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let names: Vec<String> = buckets
+        .list()
+        .await
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket.name.clone())
+        .collect();
+    assert_eq!(names, ["first", "second", "third"]);
+    let requests = requests.await.unwrap();
+    assert!(!requests[0].lines().next().unwrap().contains("marker="));
+    assert!(requests[1]
+        .lines()
+        .next()
+        .unwrap()
+        .contains("marker=second-page"));
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_get_blob_range() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::blob::{Blob, ByteRange};
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    let partial = response(
+        "206 Partial Content",
+        &[
+            ("content-range", "bytes 6-10/11"),
+            ("content-type", "text/plain"),
+            ("etag", "\"0x8D9\""),
+            ("last-modified", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-creation-time", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-blob-type", "BlockBlob"),
+            ("x-ms-lease-status", "unlocked"),
+            ("x-ms-lease-state", "available"),
+            ("x-ms-server-encrypted", "true"),
+            ("x-ms-request-id", "9c4e9c1a-201e-0004-2f5d-56f2d3000000"),
+            ("date", "Tue, 01 Jun 2021 00:00:00 GMT"),
+        ],
+        "world",
+    );
+    // only suffix ranges look the size up first
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        partial.clone(),
+        partial.clone(),
+        properties(),
+        partial,
+    ])
+    .await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    for range in [
+        ByteRange::Bounded(6, 10),
+        ByteRange::From(6),
+        ByteRange::Suffix(5),
+    ] {
+        let mut blob = bucket.get_blob("hello.txt", Some(range)).await.unwrap();
+        assert_eq!(blob.content_range(), Some("bytes 6-10/11"));
+        assert_eq!(blob.read().await.unwrap(), "world");
+    }
+    let requests = requests.await.unwrap();
+    assert!(requests[0].contains("x-ms-range: bytes=6-10"));
+    // open ended ranges end past any blob, azure stops at its end
+    let open_ended = format!("x-ms-range: bytes=6-{}\n", u64::MAX - 1);
+    assert!(requests[1].contains(&open_ended));
+    assert!(
+        requests[2].starts_with("HEAD /devstoreaccount1/waihona/hello.txt ")
+    );
+    assert!(requests[3].contains("x-ms-range: bytes=6-10"));
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_get_blob_without_range() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::Bucket;
+    use crate::types::retry::RetryPolicy;
+    use futures::TryStreamExt;
+    let chunk = response(
+        "206 Partial Content",
+        &[
+            ("content-range", "bytes 0-10/11"),
+            ("content-type", "image/png"),
+            ("etag", "\"0x8D9\""),
+            ("last-modified", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-creation-time", "Tue, 01 Jun 2021 00:00:00 GMT"),
+            ("x-ms-blob-type", "BlockBlob"),
+            ("x-ms-lease-status", "unlocked"),
+            ("x-ms-lease-state", "available"),
+            ("x-ms-server-encrypted", "true"),
+            ("x-ms-request-id", "9c4e9c1a-201e-0004-2f5d-56f2d3000000"),
+            ("date", "Tue, 01 Jun 2021 00:00:00 GMT"),
+        ],
+        "hello world",
+    );
+    let (endpoint, requests) =
+        crate::tests::receive_each(vec![properties(), chunk]).await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let mut blob = bucket.get_blob("photo.png", None).await.unwrap();
+    assert_eq!(blob.properties().size, Some(11));
+    assert_eq!(blob.content_range(), None);
+    // the content is only downloaded when streamed, a range at a time
+    let read: Vec<_> = blob
+        .read_stream()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(read.concat(), b"hello world");
+    let requests = requests.await.unwrap();
+    assert!(
+        requests[0].starts_with("HEAD /devstoreaccount1/waihona/photo.png ")
+    );
+    assert!(requests[1].starts_with("GET /devstoreaccount1/waihona/photo.png "));
+    assert!(requests[1].contains("x-ms-range: bytes=0-10\n"));
+}
+
+#[tokio::test]
+#[cfg(feature = "azure")]
+async fn test_block_status() {
+    use crate::providers;
+    use crate::tests::response;
+    use crate::types::retry::RetryPolicy;
+    use bytes::Bytes;
+    let (endpoint, requests) = crate::tests::receive_each(vec![
+        response("201 Created", &[], ""),
+        response("201 Created", &[], ""),
+        properties(),
+    ])
+    .await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let content = futures::stream::once(async { Ok(Bytes::from("Hello")) });
+    bucket
+        .write_blob_blocks("blocks.bin", Box::pin(content), None)
+        .await
+        .unwrap();
+    let requests = requests.await.unwrap();
+    assert!(requests[0].contains("?comp=block&blockid=MDAwMDAwMDA%3D "));
+    assert!(requests[1].contains("?comp=blocklist "));
+    // put block answers 201, any other success is unexpected
+    let (endpoint, _requests) =
+        crate::tests::receive_each(vec![response("200 OK", &[], "")]).await;
+    let buckets = providers::azure::AzureBuckets::emulator(&endpoint).unwrap();
+    let bucket = buckets
+        .bucket("waihona")
+        .with_retry_policy(RetryPolicy::none());
+    let content = futures::stream::once(async { Ok(Bytes::from("Hello")) });
+    let resp = bucket
+        .write_blob_blocks("blocks.bin", Box::pin(content), None)
+        .await;
+    assert!(resp.is_err());
 }
@@ -0,0 +1,23 @@
+#[test]
+fn test_byte_range() {
+    use crate::types::blob::ByteRange;
+    let bounded: ByteRange = "bytes=0-99".parse().unwrap();
+    assert_eq!(bounded, (0..=99).into());
+    assert_eq!(bounded.to_string(), "bytes=0-99");
+    assert_eq!(bounded.resolve(50).unwrap(), (0, 49));
+    assert_eq!(bounded.content_range(50).unwrap(), "bytes 0-49/50");
+    let open_ended: ByteRange = "bytes=100-".parse().unwrap();
+    assert_eq!(open_ended, (100..).into());
+    assert_eq!(open_ended.resolve(1000).unwrap(), (100, 999));
+    assert!(open_ended.resolve(100).is_err());
+    let suffix: ByteRange = "bytes=-100".parse().unwrap();
+    assert_eq!(suffix, ByteRange::Suffix(100));
+    assert_eq!(suffix.to_string(), "bytes=-100");
+    assert_eq!(suffix.content_range(1000).unwrap(), "bytes 900-999/1000");
+    assert_eq!(suffix.resolve(10).unwrap(), (0, 9));
+    assert!(ByteRange::Suffix(0).resolve(10).is_err());
+    assert!(ByteRange::Bounded(5, 2).resolve(10).is_err());
+    assert!(suffix.resolve(0).is_err());
+    assert!("bytes=-".parse::<ByteRange>().is_err());
+    assert!("0-99".parse::<ByteRange>().is_err());
+}
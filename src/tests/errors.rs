@@ -0,0 +1,39 @@
+#[test]
+fn test_error_kinds() {
+    use crate::types::errors::{
+        BlobError, BucketError, ErrorDetails, ErrorKind,
+    };
+    use std::error::Error;
+    assert_eq!(ErrorKind::from_status(404), ErrorKind::NotFound);
+    assert_eq!(ErrorKind::from_status(403), ErrorKind::PermissionDenied);
+    assert_eq!(ErrorKind::from_status(412), ErrorKind::PreconditionFailed);
+    assert_eq!(ErrorKind::from_status(429), ErrorKind::Throttled);
+    assert_eq!(ErrorKind::from_status(504), ErrorKind::Timeout);
+    assert_eq!(ErrorKind::from_status(400), ErrorKind::InvalidArgument);
+    assert_eq!(ErrorKind::from_status(409), ErrorKind::Conflict);
+    assert_eq!(ErrorKind::from_status(500), ErrorKind::Other);
+    let source = std::io::Error::other("reset");
+    let error = BlobError::GetError(
+        ErrorDetails::from_status(412, "condition not met")
+            .with_request_id("req-1")
+            .with_source(source),
+    );
+    assert_eq!(error.kind(), ErrorKind::PreconditionFailed);
+    assert_eq!(error.status(), Some(412));
+    assert_eq!(error.request_id(), Some("req-1"));
+    assert_eq!(
+        error.to_string(),
+        "GetError: condition not met (status 412) (request id req-1)"
+    );
+    assert_eq!(error.source().unwrap().to_string(), "reset");
+    // wrapping keeps kind, status and request id
+    let wrapped = BlobError::CopyError(error.into());
+    assert_eq!(wrapped.kind(), ErrorKind::PreconditionFailed);
+    assert_eq!(wrapped.status(), Some(412));
+    assert_eq!(wrapped.request_id(), Some("req-1"));
+    assert!(wrapped.source().unwrap().is::<BlobError>());
+    assert_eq!(BucketError::NotFound.kind(), ErrorKind::NotFound);
+    assert!(BucketError::NotFound.source().is_none());
+    let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+    assert_eq!(ErrorDetails::from(missing).kind(), ErrorKind::NotFound);
+}
@@ -0,0 +1,70 @@
+#[test]
+#[cfg(all(feature = "aws", feature = "gcp", feature = "azure"))]
+fn test_parse_storage_url() {
+    use crate::providers::any::Provider;
+    use crate::providers::location::StorageUrl;
+    let url =
+        StorageUrl::parse("s3://waihona/photos/2021?region=us-east-2").unwrap();
+    assert_eq!(url.provider, Provider::Aws);
+    assert_eq!(url.location, "us-east-2");
+    assert_eq!(url.bucket, "waihona");
+    assert_eq!(url.prefix, "photos/2021/");
+    let url: StorageUrl =
+        "gs://waihona?project=gcp-project-name".parse().unwrap();
+    assert_eq!(url.provider, Provider::Gcp);
+    assert_eq!(url.location, "gcp-project-name");
+    assert_eq!(url.bucket, "waihona");
+    assert_eq!(url.prefix, "");
+    let url =
+        StorageUrl::parse("az://waihona/my%20folder/?account=waihona").unwrap();
+    assert_eq!(url.provider, Provider::Azure);
+    assert_eq!(url.location, "waihona");
+    assert_eq!(url.prefix, "my folder/");
+    assert!(StorageUrl::parse("ftp://waihona/photos").is_err());
+    assert!(StorageUrl::parse("s3:///photos").is_err());
+    assert!(StorageUrl::parse("not a url").is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "local")]
+async fn test_open_url_local() {
+    use crate::providers;
+    use crate::providers::any::Provider;
+    use crate::providers::location::StorageUrl;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    let root = tempfile::tempdir().unwrap();
+    let mut local_buckets = providers::local::LocalBuckets::new(root.path());
+    let waihona = local_buckets.create("waihona", None).await.unwrap();
+    waihona
+        .write_blob("outside.txt", Some(Bytes::from("Hello")), None)
+        .await
+        .unwrap();
+    let url = format!(
+        "file://{}/waihona/photos?root={}",
+        root.path().display(),
+        root.path().display()
+    );
+    let parsed = StorageUrl::parse(&url).unwrap();
+    assert_eq!(parsed.provider, Provider::Local);
+    assert_eq!(parsed.bucket, "waihona");
+    assert_eq!(parsed.prefix, "photos/");
+    let photos = crate::open_url(&url).await.unwrap();
+    photos
+        .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+        .await
+        .unwrap();
+    assert!(waihona.get_blob("photos/hello.txt", None).await.is_ok());
+    assert!(photos.get_blob("hello.txt", None).await.is_ok());
+    let (blobs, _) = photos.list_blobs(None).await.unwrap();
+    assert_eq!(blobs.len(), 1);
+    let blobs: Vec<_> = photos.list_all_blobs().try_collect().await.unwrap();
+    assert_eq!(blobs.len(), 1);
+    assert!(photos.delete_blob("hello.txt").await.unwrap());
+    // without a root the last directory is the bucket
+    let url = format!("file://{}/waihona", root.path().display());
+    let bucket = crate::open_url(&url).await.unwrap();
+    assert_eq!(bucket.prefix(), "");
+    assert!(bucket.get_blob("outside.txt", None).await.is_ok());
+}
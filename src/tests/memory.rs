@@ -0,0 +1,421 @@
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_bucket_lifecycle() {
+    use crate::providers;
+    use crate::types::bucket::Buckets;
+    use crate::types::errors::ErrorKind;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    assert!(!memory_buckets.exists("waihona").await.unwrap());
+    memory_buckets.create("waihona", None).await.unwrap();
+    let duplicate = memory_buckets.create("waihona", None).await;
+    assert_eq!(duplicate.unwrap_err().kind(), ErrorKind::Conflict);
+    // clones share the same store
+    let mut shared = memory_buckets.clone();
+    assert!(shared.exists("waihona").await.unwrap());
+    let resp = shared.list().await.unwrap();
+    assert_eq!(resp.len(), 1);
+    assert_eq!(resp[0].name, "waihona");
+    assert!(memory_buckets.delete("waihona").await.unwrap());
+    assert!(!shared.delete("waihona").await.unwrap());
+    assert!(shared.open("waihona").await.is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_write_read_blob() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    let content = Some(Bytes::from(r"{'example': 1}"));
+    let mut new = waihona
+        .write_blob("new.json", content, Some("application/json".to_owned()))
+        .await
+        .unwrap();
+    let read = new.read().await.unwrap();
+    assert!(read.eq(&Bytes::from(r"{'example': 1}")));
+    let mut ranged = waihona
+        .get_blob("new.json", Some((2..=8).into()))
+        .await
+        .unwrap();
+    let read = ranged.read().await.unwrap();
+    assert!(read.eq(&Bytes::from("example")));
+    let mut open_ended = waihona
+        .get_blob("new.json", Some((12..).into()))
+        .await
+        .unwrap();
+    let read = open_ended.read().await.unwrap();
+    assert!(read.eq(&Bytes::from("1}")));
+    assert!(waihona
+        .get_blob("new.json", Some((20..=30).into()))
+        .await
+        .is_err());
+    assert!(waihona.get_blob("missing.json", None).await.is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_copy_delete_blob() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    memory_buckets.create("mythra", None).await.unwrap();
+    let blob = waihona
+        .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+        .await
+        .unwrap();
+    assert!(waihona
+        .copy_blob("hello.txt", "mythra", None)
+        .await
+        .is_err());
+    assert!(waihona
+        .copy_blob("hello.txt", "fake-bucket/hello.txt", None)
+        .await
+        .is_err());
+    assert!(blob.copy("waihona/folder/copy.txt", None).await.unwrap());
+    let mut copied = waihona
+        .copy_blob("folder/copy.txt", "mythra/copied.txt", None)
+        .await
+        .unwrap();
+    let read = copied.read().await.unwrap();
+    assert!(read.eq(&Bytes::from("Hello world")));
+    assert!(memory_buckets.delete("mythra").await.is_err());
+    assert!(copied.delete().await.unwrap());
+    assert!(copied.delete().await.is_err());
+    assert!(memory_buckets.delete("mythra").await.unwrap());
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_list_blobs_pagination() {
+    use crate::providers;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    for i in 0..2500 {
+        waihona
+            .write_blob(&format!("{:04}.txt", i), Some(Bytes::new()), None)
+            .await
+            .unwrap();
+    }
+    let mut marker = None;
+    let mut pages = Vec::new();
+    loop {
+        let (blobs, next) = waihona.list_blobs(marker).await.unwrap();
+        pages.push(blobs.len());
+        marker = next;
+        if marker.is_none() {
+            break;
+        }
+    }
+    assert_eq!(pages, vec![1000, 1000, 500]);
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_list_all_blobs() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    for i in 0..2500 {
+        waihona
+            .write_blob(&format!("{:04}.txt", i), Some(Bytes::new()), None)
+            .await
+            .unwrap();
+    }
+    let blobs: Vec<_> = waihona.list_all_blobs().try_collect().await.unwrap();
+    assert_eq!(blobs.len(), 2500);
+    assert_eq!(blobs[2499].properties().key, "2499.txt");
+    let empty = memory_buckets.create("empty", None).await.unwrap();
+    let blobs: Vec<_> = empty.list_all_blobs().try_collect().await.unwrap();
+    assert!(blobs.is_empty());
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_list_blobs_with_options() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets, ListOptions};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    for name in &[
+        "photos/2020/a.png",
+        "photos/2021/b.png",
+        "photos/2021/c.png",
+        "photos/cover.png",
+        "videos/d.mp4",
+    ] {
+        waihona
+            .write_blob(name, Some(Bytes::new()), None)
+            .await
+            .unwrap();
+    }
+    let options = ListOptions {
+        prefix: Some("photos/".to_owned()),
+        delimiter: Some("/".to_owned()),
+        ..Default::default()
+    };
+    let list = waihona.list_blobs_with_options(options).await.unwrap();
+    let keys: Vec<&str> = list
+        .blobs
+        .iter()
+        .map(|blob| blob.properties().key.as_str())
+        .collect();
+    assert_eq!(keys, vec!["photos/cover.png"]);
+    assert_eq!(list.prefixes, vec!["photos/2020/", "photos/2021/"]);
+    assert!(list.marker.is_none());
+    // pages count blobs and common prefixes alike
+    let mut options = ListOptions {
+        delimiter: Some("/".to_owned()),
+        prefix: Some("photos/".to_owned()),
+        max_results: Some(2),
+        ..Default::default()
+    };
+    let list = waihona
+        .list_blobs_with_options(options.clone())
+        .await
+        .unwrap();
+    assert!(list.blobs.is_empty());
+    assert_eq!(list.prefixes, vec!["photos/2020/", "photos/2021/"]);
+    options.marker = list.marker;
+    let list = waihona.list_blobs_with_options(options).await.unwrap();
+    assert_eq!(list.blobs.len(), 1);
+    assert!(list.prefixes.is_empty());
+    assert!(list.marker.is_none());
+    let options = ListOptions {
+        start_after: Some("photos/2021/b.png".to_owned()),
+        ..Default::default()
+    };
+    let list = waihona.list_blobs_with_options(options).await.unwrap();
+    let keys: Vec<&str> = list
+        .blobs
+        .iter()
+        .map(|blob| blob.properties().key.as_str())
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["photos/2021/c.png", "photos/cover.png", "videos/d.mp4"]
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_read_stream() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    waihona
+        .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+        .await
+        .unwrap();
+    let mut blob = waihona
+        .get_blob("hello.txt", Some((6..).into()))
+        .await
+        .unwrap();
+    let chunks: Vec<Bytes> = blob
+        .read_stream()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), b"world");
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_write_blob_stream() {
+    use crate::providers;
+    use crate::types::blob::Blob;
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    let chunks = futures::stream::iter(vec![
+        Ok(Bytes::from("Hello")),
+        Ok(Bytes::from(" ")),
+        Ok(Bytes::from("world")),
+    ]);
+    let mut blob = waihona
+        .write_blob_stream("hello.txt", Box::pin(chunks), None)
+        .await
+        .unwrap();
+    let read = blob.read().await.unwrap();
+    assert!(read.eq(&Bytes::from("Hello world")));
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_stat_blob() {
+    use crate::providers;
+    use crate::types::blob::{Blob, ByteRange};
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    let written = waihona
+        .write_blob(
+            "hello.txt",
+            Some(Bytes::from("Hello world")),
+            Some("text/plain".to_owned()),
+        )
+        .await
+        .unwrap();
+    let written = written.properties();
+    let mut blob = waihona.stat_blob("hello.txt").await.unwrap();
+    let properties = blob.properties().clone();
+    assert_eq!(&properties, written);
+    assert_eq!(properties.key, "hello.txt");
+    assert_eq!(properties.bucket, "waihona");
+    assert_eq!(properties.size, Some(11));
+    assert_eq!(properties.content_type.as_deref(), Some("text/plain"));
+    assert!(properties.e_tag.is_some());
+    assert!(properties.last_modified.is_some());
+    let (listed, _) = waihona.list_blobs(None).await.unwrap();
+    assert_eq!(listed[0].properties(), &properties);
+    let ranged = waihona
+        .get_blob("hello.txt", Some(ByteRange::Suffix(5)))
+        .await
+        .unwrap();
+    assert_eq!(ranged.properties().size, Some(5));
+    assert_eq!(ranged.content_range(), Some("bytes 6-10/11"));
+    assert_eq!(blob.read().await.unwrap(), Bytes::from("Hello world"));
+    assert!(waihona.stat_blob("missing.txt").await.is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_signed_url() {
+    use crate::providers;
+    use crate::types::blob::{Blob, HttpMethod};
+    use crate::types::bucket::{Bucket, Buckets};
+    use crate::types::errors::ErrorKind;
+    use bytes::Bytes;
+    use std::time::Duration;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    let blob = waihona
+        .write_blob("hello.txt", Some(Bytes::from("Hello world")), None)
+        .await
+        .unwrap();
+    let expires_in = Duration::from_secs(900);
+    let resp = blob.signed_url(HttpMethod::Get, expires_in).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Unsupported);
+    let resp = waihona
+        .signed_url("hello.txt", HttpMethod::Put, expires_in)
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Unsupported);
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_write_options() {
+    use crate::providers;
+    use crate::types::blob::{Blob, WriteOptions};
+    use crate::types::bucket::{Bucket, Buckets};
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    let options = WriteOptions {
+        content_type: Some("image/png".to_owned()),
+        cache_control: Some("public, max-age=3600".to_owned()),
+        content_encoding: Some("gzip".to_owned()),
+        content_disposition: Some("inline".to_owned()),
+        content_language: Some("en-US".to_owned()),
+        storage_class: Some("STANDARD".to_owned()),
+        metadata: std::iter::once(("team".to_owned(), "cdn".to_owned()))
+            .collect(),
+    };
+    let written = waihona
+        .write_blob_with_options(
+            "photo.png",
+            Some(Bytes::from("Hello world")),
+            &options,
+        )
+        .await
+        .unwrap();
+    let properties = waihona.stat_blob("photo.png").await.unwrap();
+    let properties = properties.properties();
+    assert_eq!(properties, written.properties());
+    assert_eq!(properties.content_type, options.content_type);
+    assert_eq!(properties.cache_control, options.cache_control);
+    assert_eq!(properties.content_encoding, options.content_encoding);
+    assert_eq!(properties.content_disposition, options.content_disposition);
+    assert_eq!(properties.content_language, options.content_language);
+    assert_eq!(properties.storage_class, options.storage_class);
+    assert_eq!(properties.metadata, options.metadata);
+    // writing again replaces every option
+    written
+        .write_with_options(
+            Some(Bytes::from("Hello")),
+            &WriteOptions::default(),
+        )
+        .await
+        .unwrap();
+    let blob = waihona.stat_blob("photo.png").await.unwrap();
+    assert_eq!(blob.properties().cache_control, None);
+    assert!(blob.properties().metadata.is_empty());
+}
+
+#[tokio::test]
+#[cfg(feature = "memory")]
+async fn test_update_blob_metadata() {
+    use crate::providers;
+    use crate::types::blob::{Blob, WriteOptions};
+    use crate::types::bucket::{Bucket, Buckets};
+    use crate::types::errors::ErrorKind;
+    use bytes::Bytes;
+    let mut memory_buckets = providers::memory::MemoryBuckets::new();
+    let waihona = memory_buckets.create("waihona", None).await.unwrap();
+    let options = WriteOptions {
+        content_type: Some("image/png".to_owned()),
+        storage_class: Some("COLD".to_owned()),
+        metadata: std::iter::once(("team".to_owned(), "cdn".to_owned()))
+            .collect(),
+        ..Default::default()
+    };
+    let mut blob = waihona
+        .write_blob_with_options(
+            "photo.png",
+            Some(Bytes::from("Hello world")),
+            &options,
+        )
+        .await
+        .unwrap();
+    let relabelled = WriteOptions {
+        cache_control: Some("no-cache".to_owned()),
+        metadata: std::iter::once(("owner".to_owned(), "media".to_owned()))
+            .collect(),
+        ..Default::default()
+    };
+    blob.set_properties(&relabelled).await.unwrap();
+    let properties = blob.properties();
+    assert_eq!(properties.content_type, None);
+    assert_eq!(properties.cache_control, relabelled.cache_control);
+    assert_eq!(properties.metadata, relabelled.metadata);
+    // the storage class and the content are kept
+    assert_eq!(properties.storage_class, options.storage_class);
+    assert_eq!(blob.read().await.unwrap(), Bytes::from("Hello world"));
+    let stat = waihona.stat_blob("photo.png").await.unwrap();
+    assert_eq!(stat.properties(), blob.properties());
+    let resp = waihona
+        .update_blob_metadata("missing.png", &relabelled)
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::NotFound);
+}
@@ -1,6 +1,143 @@
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local",
+    feature = "memory"
+))]
+pub mod any;
 #[cfg(feature = "aws")]
 pub mod aws;
 #[cfg(feature = "azure")]
 pub mod azure;
+pub mod blob;
+pub mod errors;
 #[cfg(feature = "gcp")]
 pub mod gcp;
+#[cfg(feature = "local")]
+pub mod local;
+#[cfg(any(
+    feature = "aws",
+    feature = "gcp",
+    feature = "azure",
+    feature = "local"
+))]
+pub mod location;
+#[cfg(feature = "memory")]
+pub mod memory;
+pub mod retry;
+pub mod timeout;
+
+/// Endpoint of a server answering a single request with status and
+/// no body, the handle resolves to the request line it received
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+pub(crate) async fn respond_once(
+    status: &'static str,
+) -> (String, tokio::task::JoinHandle<String>) {
+    let (endpoint, request) = receive_once(status).await;
+    let handle = tokio::spawn(async move {
+        let request = request.await.unwrap();
+        request.lines().next().unwrap_or_default().to_owned()
+    });
+    (endpoint, handle)
+}
+
+/// Endpoint of a server answering a single request with status and
+/// no body, the handle resolves to the whole request it received
+/// with its header names lowercased
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+pub(crate) async fn receive_once(
+    status: &'static str,
+) -> (String, tokio::task::JoinHandle<String>) {
+    let (endpoint, requests) =
+        receive_each(vec![response(status, &[], "")]).await;
+    let handle = tokio::spawn(async move {
+        requests.await.unwrap().pop().unwrap_or_default()
+    });
+    (endpoint, handle)
+}
+
+/// Raw HTTP response with status, headers and body, its content
+/// length is that of body unless headers set one
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+pub(crate) fn response(
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\nconnection: close\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+    {
+        response.push_str(&format!("content-length: {}\r\n", body.len()));
+    }
+    format!("{}\r\n{}", response, body)
+}
+
+/// Endpoint of a server answering requests with responses in turn,
+/// the handle resolves to the requests it received as receive_once
+/// does, once every response was sent
+#[cfg(any(feature = "aws", feature = "gcp", feature = "azure"))]
+pub(crate) async fn receive_each(
+    responses: Vec<String>,
+) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let mut received = Vec::new();
+        for response in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            let mut expected = None;
+            loop {
+                if expected.is_none() {
+                    let head =
+                        request.windows(4).position(|w| w == b"\r\n\r\n");
+                    expected = head.map(|end| {
+                        let head = String::from_utf8_lossy(&request[..end]);
+                        let length = head
+                            .lines()
+                            .filter_map(|line| line.split_once(':'))
+                            .find(|(name, _)| {
+                                name.eq_ignore_ascii_case("content-length")
+                            })
+                            .and_then(|(_, value)| value.trim().parse().ok());
+                        end + 4 + length.unwrap_or(0)
+                    });
+                }
+                if matches!(expected, Some(expected) if request.len() >= expected)
+                {
+                    break;
+                }
+                let read = socket.read(&mut buf).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..read]);
+            }
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let request = String::from_utf8_lossy(&request).into_owned();
+            let (head, body) =
+                request.split_once("\r\n\r\n").unwrap_or_default();
+            let mut lines = head.lines();
+            let mut lowered = lines.next().unwrap_or_default().to_owned();
+            for line in lines {
+                let (name, value) = line.split_once(':').unwrap_or((line, ""));
+                lowered.push_str(&format!(
+                    "\n{}:{}",
+                    name.to_ascii_lowercase(),
+                    value
+                ));
+            }
+            received.push(format!("{}\n\n{}", lowered, body));
+        }
+        received
+    });
+    (endpoint, handle)
+}
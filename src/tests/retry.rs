@@ -0,0 +1,68 @@
+#[tokio::test]
+async fn test_retry_policy() {
+    use crate::types::errors::{ErrorDetails, ErrorKind};
+    use crate::types::retry::{is_retryable, RetryPolicy};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(2),
+        jitter: false,
+        ..Default::default()
+    };
+    let attempts = AtomicU32::new(0);
+    // throttled twice, then succeeds
+    let resp = policy
+        .retry(|| async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(ErrorDetails::from_status(503, "SlowDown"))
+            } else {
+                Ok("written")
+            }
+        })
+        .await;
+    assert_eq!(resp.unwrap(), "written");
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    // a missing blob would be missing again
+    attempts.store(0, Ordering::SeqCst);
+    let resp: Result<(), _> = policy
+        .retry(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ErrorDetails::from_status(404, "NoSuchKey"))
+        })
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    // gives up after max_attempts
+    attempts.store(0, Ordering::SeqCst);
+    let resp: Result<(), _> = policy
+        .retry(|| async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ErrorDetails::new(ErrorKind::Connection, "connection reset"))
+        })
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Connection);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(policy.delay(0), Duration::from_millis(1));
+    assert_eq!(policy.delay(1), Duration::from_millis(2));
+    assert_eq!(policy.delay(40), Duration::from_millis(2));
+    let jittered = RetryPolicy {
+        jitter: true,
+        ..policy
+    };
+    assert!(jittered.delay(5) <= Duration::from_millis(2));
+    assert_eq!(RetryPolicy::none().max_attempts, 1);
+    assert!(is_retryable(&ErrorDetails::from_status(
+        500,
+        "InternalError"
+    )));
+    assert!(!is_retryable(&ErrorDetails::from_status(
+        501,
+        "NotImplemented"
+    )));
+    assert!(!is_retryable(&ErrorDetails::from_status(
+        403,
+        "AccessDenied"
+    )));
+}
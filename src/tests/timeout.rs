@@ -0,0 +1,114 @@
+#[tokio::test]
+async fn test_timeout_config() {
+    use crate::types::errors::{ErrorDetails, ErrorKind};
+    use crate::types::retry::RetryPolicy;
+    use crate::types::timeout::TimeoutConfig;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    let policy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        jitter: false,
+        ..Default::default()
+    };
+    let timeouts = TimeoutConfig {
+        request: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let attempts = AtomicU32::new(0);
+    // every attempt hangs, each is timed out and retried
+    let resp: Result<(), ErrorDetails> = timeouts
+        .send(&policy, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Timeout);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    // the first attempt hangs, the retry answers in time
+    attempts.store(0, Ordering::SeqCst);
+    let resp = timeouts
+        .send(&policy, || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+            Ok("read")
+        })
+        .await;
+    assert_eq!(resp.unwrap(), "read");
+    // the operation timeout bounds all attempts together
+    attempts.store(0, Ordering::SeqCst);
+    let timeouts = TimeoutConfig {
+        request: Some(Duration::from_secs(5)),
+        operation: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let resp: Result<(), ErrorDetails> = timeouts
+        .send(&policy, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Timeout);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    // requests sent once get the shorter of both timeouts
+    let resp: Result<(), ErrorDetails> = timeouts
+        .send_once(async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        })
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Timeout);
+    // no timeouts by default
+    let resp = TimeoutConfig::default()
+        .send(&RetryPolicy::none(), || async {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            Ok("written")
+        })
+        .await;
+    assert_eq!(resp.unwrap(), "written");
+}
+
+#[tokio::test]
+async fn test_timeout_scope() {
+    use crate::types::errors::{ErrorDetails, ErrorKind};
+    use crate::types::retry::RetryPolicy;
+    use crate::types::timeout::TimeoutConfig;
+    use std::time::Duration;
+    let client = TimeoutConfig::default();
+    let slow = || async {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        Ok(())
+    };
+    // a scope bounds the calls made within it
+    let fast = TimeoutConfig {
+        request: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let resp: Result<(), ErrorDetails> = fast
+        .scope(async { client.send(&RetryPolicy::none(), slow).await })
+        .await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Timeout);
+    let resp: Result<(), ErrorDetails> =
+        fast.scope(async { client.send_once(slow()).await }).await;
+    assert_eq!(resp.unwrap_err().kind(), ErrorKind::Timeout);
+    // and replaces the timeouts of the client
+    let client = TimeoutConfig {
+        operation: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let resp = TimeoutConfig::default()
+        .scope(async {
+            client
+                .send(&RetryPolicy::none(), || async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok("read")
+                })
+                .await
+        })
+        .await;
+    assert_eq!(resp.unwrap(), "read");
+}
@@ -1,11 +1,210 @@
-use crate::types::errors::BlobResult;
+use crate::types::errors::{BlobError, BlobResult, ErrorDetails, ErrorKind};
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{Stream, TryStreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{RangeFrom, RangeInclusive};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
+
+/// Content of a blob as a stream of chunks
+pub type BlobStream = Pin<Box<dyn Stream<Item = BlobResult<Bytes>> + Send>>;
+
+/// Stream the content of a reader e.g a file or an HTTP request body,
+/// to be written with Bucket::write_blob_stream
+pub fn stream_from_reader<R>(reader: R) -> BlobStream
+where
+    R: AsyncRead + Send + 'static,
+{
+    Box::pin(
+        ReaderStream::new(reader).map_err(|e| BlobError::ReadError(e.into())),
+    )
+}
+
+/// Properties of a blob, the same across providers.
+/// Fields a provider did not return are None
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlobProperties {
+    /// Path of the blob within its bucket e.g "pictures/image1.png"
+    pub key: String,
+    pub bucket: String,
+    /// Size in bytes, of the requested range only on ranged blobs
+    pub size: Option<u64>,
+    pub e_tag: Option<String>,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_language: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub created: Option<DateTime<Utc>>,
+    /// Storage class on aws and gcp, access tier on azure
+    pub storage_class: Option<String>,
+    /// User defined metadata
+    pub metadata: HashMap<String, String>,
+}
+
+impl BlobProperties {
+    pub fn new(key: impl Into<String>, bucket: impl Into<String>) -> Self {
+        BlobProperties {
+            key: key.into(),
+            bucket: bucket.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Headers and metadata a blob is written with, see
+/// Bucket::write_blob_with_options. Fields left None are not set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub content_type: Option<String>,
+    /// Served as the Cache-Control header e.g "public, max-age=3600"
+    pub cache_control: Option<String>,
+    /// Encoding the content was compressed with e.g "gzip"
+    pub content_encoding: Option<String>,
+    /// e.g "attachment; filename=\"report.pdf\""
+    pub content_disposition: Option<String>,
+    /// e.g "en-US"
+    pub content_language: Option<String>,
+    /// Storage class on aws and gcp e.g "STANDARD_IA" or "NEARLINE",
+    /// access tier on azure e.g "Cool"
+    pub storage_class: Option<String>,
+    /// User defined metadata
+    pub metadata: HashMap<String, String>,
+}
+
+/// Range of bytes of a blob to get, see Bucket::get_blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Bytes from start to end, both included, e.g (0..=99).into()
+    Bounded(u64, u64),
+    /// Bytes from an offset to the end of the blob, e.g (100..).into()
+    From(u64),
+    /// Last N bytes of the blob
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Resolve the range against a blob of size bytes, returns the
+    /// inclusive (start, end) offsets, end is capped to the last byte
+    pub fn resolve(&self, size: u64) -> BlobResult<(u64, u64)> {
+        let last = size.checked_sub(1);
+        let (start, end) = match (*self, last) {
+            (ByteRange::Bounded(start, end), Some(last)) => {
+                (start, end.min(last))
+            }
+            (ByteRange::From(start), Some(last)) => (start, last),
+            (ByteRange::Suffix(len), Some(last)) if len > 0 => {
+                (size.saturating_sub(len), last)
+            }
+            _ => return Err(self.invalid()),
+        };
+        if start > end {
+            return Err(self.invalid());
+        }
+        Ok((start, end))
+    }
+
+    /// Content range of the resolved range as returned by
+    /// providers, e.g "bytes 0-99/1000"
+    pub fn content_range(&self, size: u64) -> BlobResult<String> {
+        let (start, end) = self.resolve(size)?;
+        Ok(format!("bytes {}-{}/{}", start, end, size))
+    }
+
+    fn invalid(&self) -> BlobError {
+        BlobError::GetError(ErrorDetails::new(
+            ErrorKind::InvalidArgument,
+            format!("Invalid range {}", self),
+        ))
+    }
+}
+
+/// HTTP Range header value, e.g "bytes=0-99", "bytes=100-"
+/// or "bytes=-100"
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ByteRange::Bounded(start, end) => {
+                write!(f, "bytes={}-{}", start, end)
+            }
+            ByteRange::From(start) => write!(f, "bytes={}-", start),
+            ByteRange::Suffix(len) => write!(f, "bytes=-{}", len),
+        }
+    }
+}
+
+/// Parse an HTTP Range header value with a single range
+impl FromStr for ByteRange {
+    type Err = BlobError;
+
+    fn from_str(range: &str) -> BlobResult<Self> {
+        let invalid = || {
+            BlobError::GetError(ErrorDetails::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid range {}", range),
+            ))
+        };
+        let spec = range.trim().strip_prefix("bytes=").ok_or_else(invalid)?;
+        let (start, end) = spec.split_once('-').ok_or_else(invalid)?;
+        let offset = |value: &str| value.parse::<u64>().map_err(|_| invalid());
+        match (start.trim(), end.trim()) {
+            ("", "") => Err(invalid()),
+            ("", len) => Ok(ByteRange::Suffix(offset(len)?)),
+            (start, "") => Ok(ByteRange::From(offset(start)?)),
+            (start, end) => {
+                Ok(ByteRange::Bounded(offset(start)?, offset(end)?))
+            }
+        }
+    }
+}
+
+impl From<RangeInclusive<u64>> for ByteRange {
+    fn from(range: RangeInclusive<u64>) -> Self {
+        ByteRange::Bounded(*range.start(), *range.end())
+    }
+}
+
+impl From<RangeFrom<u64>> for ByteRange {
+    fn from(range: RangeFrom<u64>) -> Self {
+        ByteRange::From(range.start)
+    }
+}
+
+/// HTTP method a signed url grants, see Bucket::signed_url
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// Download the blob
+    Get,
+    /// Upload the blob, replacing it if it exists
+    Put,
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpMethod::Get => write!(f, "GET"),
+            HttpMethod::Put => write!(f, "PUT"),
+        }
+    }
+}
 
 #[async_trait]
 /// Blob can be used to write to blob, read from blob
 /// and delete blob
 pub trait Blob {
+    /// Properties of the blob as returned by the request
+    /// it was obtained through
+    fn properties(&self) -> &BlobProperties;
+    /// Range of the content held by a ranged blob
+    /// e.g "bytes 0-99/1000", see Bucket::get_blob
+    fn content_range(&self) -> Option<&str>;
     /// Delete blob
     async fn delete(&self) -> BlobResult<bool>;
     /// copy blob
@@ -19,7 +218,37 @@ pub trait Blob {
         &self,
         content: Option<Bytes>,
         content_type: Option<String>,
+    ) -> BlobResult<bool> {
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        self.write_with_options(content, &options).await
+    }
+    /// Write to blob with the headers and metadata of options,
+    /// see Bucket::write_blob_with_options
+    async fn write_with_options(
+        &self,
+        content: Option<Bytes>,
+        options: &WriteOptions,
     ) -> BlobResult<bool>;
+    /// Replace the headers and metadata of blob with those of options
+    /// without rewriting its content, its properties become those of
+    /// the updated blob, see Bucket::update_blob_metadata
+    async fn set_properties(
+        &mut self,
+        options: &WriteOptions,
+    ) -> BlobResult<()>;
     /// Read from blob
     async fn read(&mut self) -> BlobResult<Bytes>;
+    /// Read from blob as a stream of chunks, so that the content
+    /// never has to fit in memory at once
+    async fn read_stream(&mut self) -> BlobResult<BlobStream>;
+    /// Url granting method on blob for expires_in,
+    /// see Bucket::signed_url
+    async fn signed_url(
+        &self,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String>;
 }
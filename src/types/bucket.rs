@@ -1,7 +1,70 @@
-use crate::types::blob::Blob;
-use crate::types::errors::{BlobResult, BucketResult};
+use crate::types::blob::{
+    Blob, BlobStream, ByteRange, HttpMethod, WriteOptions,
+};
+use crate::types::errors::{BlobError, BlobResult, BucketResult};
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::stream::{self, Stream, TryStreamExt};
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// Options narrowing down the blobs listed by
+/// Bucket::list_blobs_with_options
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListOptions {
+    /// Only list blobs whose path starts with prefix e.g "photos/2021/"
+    pub prefix: Option<String>,
+    /// Group blobs whose path after the prefix contains the delimiter
+    /// into common prefixes, e.g "/" to browse a single "directory"
+    pub delimiter: Option<String>,
+    /// Only list blobs whose path comes after start_after
+    pub start_after: Option<String>,
+    /// Maximum number of blobs and common prefixes in a page,
+    /// the provider's page size when None
+    pub max_results: Option<usize>,
+    /// Marker of the page to list, as returned by the previous page
+    pub marker: Option<String>,
+}
+
+/// Page of blobs returned by Bucket::list_blobs_with_options
+#[derive(Debug)]
+pub struct BlobList<P> {
+    pub blobs: Vec<P>,
+    /// Common prefixes ("directories") of blobs grouped
+    /// by the delimiter, e.g "photos/2021/"
+    pub prefixes: Vec<String>,
+    /// Marker of the next page, None on the last page
+    pub marker: Option<String>,
+}
+
+/// Conditions a browser form upload must meet, see Bucket::post_policy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PostConditions {
+    /// Path of the uploaded blob must start with key_prefix,
+    /// the empty default allows any path
+    pub key_prefix: String,
+    /// Smallest and largest size of the upload in bytes
+    pub content_length_range: Option<RangeInclusive<u64>>,
+    /// Content type the blob is uploaded with
+    pub content_type: Option<String>,
+}
+
+/// Form a browser posts to upload a blob, returned by
+/// Bucket::post_policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostForm {
+    /// Url the form is posted to
+    pub url: String,
+    /// Fields the form must include as they are, in order. Besides
+    /// them the form sets a "key" field, the path of the blob, and
+    /// ends with the "file" field
+    pub fields: Vec<(String, String)>,
+}
+
+/// Every blob of a bucket, pages are listed as the stream is polled
+pub type BlobListStream<'a, P> =
+    Pin<Box<dyn Stream<Item = BlobResult<P>> + Send + 'a>>;
 
 /// Collection of buckets, can be used to list, create
 /// open, delete buckets
@@ -11,7 +74,8 @@ where
     T: Bucket<P>,
     P: Blob,
 {
-    /// Open an existing bucket
+    /// Open an existing bucket, fails with BucketError::NotFound or
+    /// BucketError::PermissionDenied when it cannot be accessed
     async fn open(&mut self, bucket_name: &str) -> BucketResult<T>;
     /// Create a bucket at location
     async fn create(
@@ -20,11 +84,12 @@ where
         location: Option<String>,
     ) -> BucketResult<T>;
     /// List all buckets
-    async fn list(&mut self) -> Vec<T>;
+    async fn list(&mut self) -> BucketResult<Vec<T>>;
     /// Delete a bucket
     async fn delete(&mut self, bucket_name: &str) -> BucketResult<bool>;
-    /// Check if a bucket exists
-    async fn exists(&mut self, bucket_name: &str) -> bool;
+    /// Check if a bucket exists, fails with
+    /// BucketError::PermissionDenied when it cannot be accessed
+    async fn exists(&mut self, bucket_name: &str) -> BucketResult<bool>;
 }
 
 /// Bucket delete single object, can create blob,
@@ -41,14 +106,46 @@ where
         &self,
         marker: Option<String>,
     ) -> BucketResult<(Vec<P>, Option<String>)>;
+    /// List blobs narrowed down by options, a page at a time.
+    /// Blobs are listed in lexicographic order of their paths
+    async fn list_blobs_with_options(
+        &self,
+        options: ListOptions,
+    ) -> BucketResult<BlobList<P>>;
+    /// List every blob of the bucket, walking pages of list_blobs
+    /// lazily. The stream ends after the first error
+    fn list_all_blobs(&self) -> BlobListStream<'_, P>
+    where
+        Self: Sync,
+        P: Send,
+    {
+        // None once the last page has been listed
+        let pages = stream::unfold(Some(None), move |marker| async move {
+            match self.list_blobs(marker?).await {
+                Ok((blobs, next)) => Some((Ok(blobs), next.map(Some))),
+                Err(e) => Some((Err(BlobError::ListError(e.into())), None)),
+            }
+        });
+        Box::pin(
+            pages
+                .map_ok(|blobs| stream::iter(blobs.into_iter().map(Ok)))
+                .try_flatten(),
+        )
+    }
     /// Retrieve a blob from this bucket
     /// Specify blob_path e.g "pictures/image1.png"
-    /// content_range is range to retrieve at once, if None, retrieve entire object
+    /// range is the bytes to retrieve, if None, retrieve entire object.
+    /// Ranged blobs only hold the requested bytes and have their
+    /// content range set e.g "bytes 0-99/1000"
     async fn get_blob(
         &self,
         blob_path: &str,
-        content_range: Option<String>,
+        range: Option<ByteRange>,
     ) -> BlobResult<P>;
+    /// Retrieve a blob's size, etag, content type, last modified time
+    /// and user metadata without downloading its content,
+    /// the content is only downloaded once the blob is read
+    async fn stat_blob(&self, blob_path: &str) -> BlobResult<P>;
     /// copy blob_path to another blob path
     /// blob_destination_path is formated as {bucket_name}/{path}
     /// e.g bucket1/folder/simple.jpeg
@@ -65,7 +162,84 @@ where
         blob_name: &str,
         content: Option<Bytes>,
         content_type: Option<String>,
+    ) -> BlobResult<P> {
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        self.write_blob_with_options(blob_name, content, &options)
+            .await
+    }
+    /// Write to a blob in bucket with the headers and metadata of
+    /// options: the object's headers on aws and gcp, the blob's
+    /// properties on azure. Metadata keys should be lowercase ASCII
+    /// as aws lowercases them and azure needs valid identifiers
+    async fn write_blob_with_options(
+        &self,
+        blob_name: &str,
+        content: Option<Bytes>,
+        options: &WriteOptions,
+    ) -> BlobResult<P>;
+    /// Write to a blob in bucket from a stream of chunks.
+    /// Content is uploaded as it arrives, so its length need not be
+    /// known upfront, see blob::stream_from_reader to write from
+    /// an AsyncRead
+    async fn write_blob_stream(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        content_type: Option<String>,
+    ) -> BlobResult<P> {
+        let options = WriteOptions {
+            content_type,
+            ..Default::default()
+        };
+        self.write_blob_stream_with_options(blob_name, content, &options)
+            .await
+    }
+    /// Write to a blob in bucket from a stream of chunks with the
+    /// headers and metadata of options, see write_blob_with_options
+    async fn write_blob_stream_with_options(
+        &self,
+        blob_name: &str,
+        content: BlobStream,
+        options: &WriteOptions,
+    ) -> BlobResult<P>;
+    /// Replace the headers and metadata of a blob with those of
+    /// options without rewriting its content, fields left None are
+    /// cleared and metadata keys options does not hold are removed.
+    /// The storage class is kept, options.storage_class is ignored.
+    /// aws copies the object onto itself, which fails for objects over
+    /// 5GB and resets their ACL, gcp patches the object and azure sets
+    /// the blob's properties then its metadata
+    async fn update_blob_metadata(
+        &self,
+        blob_path: &str,
+        options: &WriteOptions,
     ) -> BlobResult<P>;
     /// Delete a blob from bucket
     async fn delete_blob(&self, blob_path: &str) -> BlobResult<bool>;
+    /// Url granting whoever holds it method on blob_path for
+    /// expires_in without credentials of their own, e.g for a browser
+    /// to download or upload directly: S3 presigned urls, GCS V4
+    /// signed urls and Azure SAS urls. Urls are signed locally with
+    /// the client's credentials, the blob need not exist.
+    /// Fails with ErrorKind::Unsupported on local and memory buckets
+    /// and on clients whose credentials cannot sign
+    async fn signed_url(
+        &self,
+        blob_path: &str,
+        method: HttpMethod,
+        expires_in: Duration,
+    ) -> BlobResult<String>;
+    /// Policy letting a browser upload a blob meeting conditions
+    /// through an HTML form for expires_in, with the provider
+    /// enforcing them: S3 presigned POSTs and GCS POST policies.
+    /// Fails with ErrorKind::Unsupported on azure, local and memory
+    /// buckets and on clients whose credentials cannot sign
+    async fn post_policy(
+        &self,
+        conditions: &PostConditions,
+        expires_in: Duration,
+    ) -> BlobResult<PostForm>;
 }
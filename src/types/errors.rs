@@ -1,46 +1,374 @@
+use std::error::Error;
+use std::fmt;
 use strum_macros::Display;
+
+/// Boxed provider error kept as the source of an error
+pub type ErrorSource = Box<dyn Error + Send + Sync>;
+
+/// Kind of failure, the same across providers
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    PermissionDenied,
+    /// A condition e.g If-Match or If-None-Match was not met
+    PreconditionFailed,
+    /// The provider is rate limiting requests
+    Throttled,
+    Timeout,
+    /// The connection failed before a response was received
+    /// e.g it was reset
+    Connection,
+    InvalidArgument,
+    /// The request conflicts with the current state e.g a bucket
+    /// that already exists
+    Conflict,
+    /// The provider or its credentials cannot do what was asked
+    /// e.g sign urls for local files
+    Unsupported,
+    /// Any other failure
+    Other,
+}
+
+impl ErrorKind {
+    /// Kind of failure of an HTTP status code
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            404 => ErrorKind::NotFound,
+            401 | 403 => ErrorKind::PermissionDenied,
+            304 | 412 => ErrorKind::PreconditionFailed,
+            429 | 503 => ErrorKind::Throttled,
+            408 | 504 => ErrorKind::Timeout,
+            400 | 411 | 416 => ErrorKind::InvalidArgument,
+            409 => ErrorKind::Conflict,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// What went wrong, carried by each error variant
+#[derive(Debug)]
+pub struct ErrorDetails {
+    kind: ErrorKind,
+    message: String,
+    status: Option<u16>,
+    request_id: Option<String>,
+    source: Option<ErrorSource>,
+}
+
+impl ErrorDetails {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        ErrorDetails {
+            kind,
+            message: message.into(),
+            status: None,
+            request_id: None,
+            source: None,
+        }
+    }
+
+    /// Details of a failed HTTP request, the kind is
+    /// derived from the status code
+    pub fn from_status(status: u16, message: impl Into<String>) -> Self {
+        ErrorDetails::new(ErrorKind::from_status(status), message)
+            .with_status(status)
+    }
+
+    /// Details of an underlying error, kept as the source
+    pub fn from_source(
+        kind: ErrorKind,
+        source: impl Into<ErrorSource>,
+    ) -> Self {
+        let source = source.into();
+        ErrorDetails::new(kind, source.to_string()).with_source(source)
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn with_source(mut self, source: impl Into<ErrorSource>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// HTTP status code of the failed request
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Provider's id of the failed request, to be quoted to support
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    pub fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(status) = self.status {
+            write!(f, " (status {})", status)?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request id {})", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<String> for ErrorDetails {
+    fn from(message: String) -> Self {
+        ErrorDetails::new(ErrorKind::Other, message)
+    }
+}
+
+impl From<&str> for ErrorDetails {
+    fn from(message: &str) -> Self {
+        ErrorDetails::new(ErrorKind::Other, message)
+    }
+}
+
 /// Bucket based errors
-#[derive(Debug, Display)]
+#[derive(Debug)]
 pub enum BucketError {
     /// Bucket specified was not found
     NotFound,
     /// Bucket error during creation
-    CreationError(String),
+    CreationError(ErrorDetails),
     /// Bucket deletion error
-    DeletionError(String),
+    DeletionError(ErrorDetails),
     /// Bucket listing error
-    ListError(String),
+    ListError(ErrorDetails),
     /// Credentials error
-    CredError(String),
+    CredError(ErrorDetails),
     /// Open error
-    OpenError(String),
+    OpenError(ErrorDetails),
+    /// Bucket exists but the credentials may not access it
+    PermissionDenied(ErrorDetails),
+}
+
+impl BucketError {
+    fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            BucketError::NotFound => None,
+            BucketError::CreationError(details)
+            | BucketError::DeletionError(details)
+            | BucketError::ListError(details)
+            | BucketError::CredError(details)
+            | BucketError::OpenError(details)
+            | BucketError::PermissionDenied(details) => Some(details),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BucketError::NotFound => "NotFound",
+            BucketError::CreationError(_) => "CreationError",
+            BucketError::DeletionError(_) => "DeletionError",
+            BucketError::ListError(_) => "ListError",
+            BucketError::CredError(_) => "CredError",
+            BucketError::OpenError(_) => "OpenError",
+            BucketError::PermissionDenied(_) => "PermissionDenied",
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BucketError::NotFound => ErrorKind::NotFound,
+            BucketError::PermissionDenied(_) => ErrorKind::PermissionDenied,
+            _ => self.details().map_or(ErrorKind::Other, ErrorDetails::kind),
+        }
+    }
+
+    /// HTTP status code of the failed request
+    pub fn status(&self) -> Option<u16> {
+        self.details().and_then(ErrorDetails::status)
+    }
+
+    /// Provider's id of the failed request
+    pub fn request_id(&self) -> Option<&str> {
+        self.details().and_then(ErrorDetails::request_id)
+    }
+}
+
+impl fmt::Display for BucketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(details) => write!(f, "{}: {}", self.name(), details),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl Error for BucketError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.details().and_then(ErrorDetails::source)
+    }
 }
 
 /// Blob based errors
-#[derive(Debug, Display)]
+#[derive(Debug)]
 pub enum BlobError {
     /// Blob specified was not found
     NotFound,
     /// Blob could not be gotten
-    GetError(String),
+    GetError(ErrorDetails),
     /// Blob could not be read
-    ReadError,
+    ReadError(ErrorDetails),
     /// Could not delete blob
-    DeletionError(String),
+    DeletionError(ErrorDetails),
     /// Could not copy blob
-    CopyError(String),
+    CopyError(ErrorDetails),
     /// Could not write blob
-    WriteError(String),
+    WriteError(ErrorDetails),
+    /// Blobs could not be listed
+    ListError(ErrorDetails),
+    /// Could not sign a url for blob
+    SignError(ErrorDetails),
+}
+
+impl BlobError {
+    fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            BlobError::NotFound => None,
+            BlobError::GetError(details)
+            | BlobError::ReadError(details)
+            | BlobError::DeletionError(details)
+            | BlobError::CopyError(details)
+            | BlobError::WriteError(details)
+            | BlobError::ListError(details)
+            | BlobError::SignError(details) => Some(details),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BlobError::NotFound => "NotFound",
+            BlobError::GetError(_) => "GetError",
+            BlobError::ReadError(_) => "ReadError",
+            BlobError::DeletionError(_) => "DeletionError",
+            BlobError::CopyError(_) => "CopyError",
+            BlobError::WriteError(_) => "WriteError",
+            BlobError::ListError(_) => "ListError",
+            BlobError::SignError(_) => "SignError",
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BlobError::NotFound => ErrorKind::NotFound,
+            _ => self.details().map_or(ErrorKind::Other, ErrorDetails::kind),
+        }
+    }
+
+    /// HTTP status code of the failed request
+    pub fn status(&self) -> Option<u16> {
+        self.details().and_then(ErrorDetails::status)
+    }
+
+    /// Provider's id of the failed request
+    pub fn request_id(&self) -> Option<&str> {
+        self.details().and_then(ErrorDetails::request_id)
+    }
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(details) => write!(f, "{}: {}", self.name(), details),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl Error for BlobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.details().and_then(ErrorDetails::source)
+    }
+}
+
+impl From<std::io::Error> for ErrorDetails {
+    fn from(error: std::io::Error) -> Self {
+        let kind = match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            std::io::ErrorKind::AlreadyExists => ErrorKind::Conflict,
+            std::io::ErrorKind::InvalidInput
+            | std::io::ErrorKind::InvalidData => ErrorKind::InvalidArgument,
+            _ => ErrorKind::Other,
+        };
+        ErrorDetails::from_source(kind, error)
+    }
+}
+
+impl From<BucketError> for ErrorDetails {
+    /// Details of a failed bucket call made on behalf of another
+    /// call, kind, status and request id are kept
+    fn from(error: BucketError) -> Self {
+        let message = error
+            .details()
+            .map_or_else(|| error.to_string(), |d| d.message.clone());
+        ErrorDetails {
+            kind: error.kind(),
+            message,
+            status: error.status(),
+            request_id: error.request_id().map(str::to_owned),
+            source: None,
+        }
+        .with_source(error)
+    }
+}
+
+impl From<BlobError> for ErrorDetails {
+    /// Details of a failed blob call made on behalf of another
+    /// call, kind, status and request id are kept
+    fn from(error: BlobError) -> Self {
+        let message = error
+            .details()
+            .map_or_else(|| error.to_string(), |d| d.message.clone());
+        ErrorDetails {
+            kind: error.kind(),
+            message,
+            status: error.status(),
+            request_id: error.request_id().map(str::to_owned),
+            source: None,
+        }
+        .with_source(error)
+    }
 }
 
 /// Provider based errors
-#[derive(Debug)]
+#[derive(Debug, Display)]
 pub enum ProviderError {
     /// Provider specified was not found
     NotFound,
 }
 
+impl Error for ProviderError {}
+
 /// Bucket Result type
 pub type BucketResult<T> = std::result::Result<T, BucketError>;
 /// Blob Result type
 pub type BlobResult<T> = std::result::Result<T, BlobError>;
+/// Provider Result type
+pub type ProviderResult<T> = std::result::Result<T, ProviderError>;
@@ -1,3 +1,5 @@
 pub mod blob;
 pub mod bucket;
 pub mod errors;
+pub mod retry;
+pub mod timeout;
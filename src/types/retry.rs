@@ -0,0 +1,105 @@
+use crate::types::errors::{ErrorDetails, ErrorKind};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Decides whether a failed request is worth retrying
+pub type RetryClassifier = fn(&ErrorDetails) -> bool;
+
+/// Throttling, timeouts, dropped connections and server errors
+/// are retried, anything else would fail again
+pub fn is_retryable(details: &ErrorDetails) -> bool {
+    match details.kind() {
+        ErrorKind::Throttled | ErrorKind::Timeout | ErrorKind::Connection => {
+            true
+        }
+        _ => matches!(details.status(), Some(500 | 502..=599)),
+    }
+}
+
+/// Controls how failed requests of a client are retried.
+/// Only requests that are safe to repeat are retried: reads, listings,
+/// deletes, copies and writes of content held in memory. Bucket
+/// creation and the start or completion of multipart uploads are not
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts made in total, 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled every retry
+    pub base_delay: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_delay: Duration,
+    /// Wait a random delay of up to the backoff instead, so clients
+    /// throttled at the same time do not retry at the same time
+    pub jitter: bool,
+    /// Failures that are retried, is_retryable by default
+    pub classifier: RetryClassifier,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            classifier: is_retryable,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy making a single attempt
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before retry number retry, counting from 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = 2u32
+            .checked_pow(retry)
+            .and_then(|factor| self.base_delay.checked_mul(factor))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+        if self.jitter {
+            backoff.mul_f64(random_fraction())
+        } else {
+            backoff
+        }
+    }
+
+    /// Send request until it succeeds, fails in a way the classifier
+    /// does not retry or max_attempts is reached
+    pub async fn retry<T, F, Fut>(
+        &self,
+        mut request: F,
+    ) -> Result<T, ErrorDetails>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ErrorDetails>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Err(details)
+                    if attempt < self.max_attempts
+                        && (self.classifier)(&details) =>
+                {
+                    tokio::time::sleep(self.delay(attempt - 1)).await;
+                    attempt += 1;
+                }
+                resp => return resp,
+            }
+        }
+    }
+}
+
+/// Random number in [0, 1), RandomState is seeded
+/// differently every time it is created
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,94 @@
+use crate::types::errors::{ErrorDetails, ErrorKind};
+use crate::types::retry::RetryPolicy;
+use std::future::Future;
+use std::time::Duration;
+
+tokio::task_local! {
+    /// Timeouts of the calls made within TimeoutConfig::scope
+    static SCOPED: TimeoutConfig;
+}
+
+/// Bounds how long requests of a client may take, None waits for as
+/// long as the provider does. Timeouts fail with ErrorKind::Timeout,
+/// which the default RetryPolicy retries.
+/// A body still streaming once get_blob has returned is not bounded.
+/// Clients keep the timeouts they were built with, see scope to give
+/// a single call its own
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeoutConfig {
+    /// Time allowed to connect to the provider, set on the HTTP client
+    /// so it only applies to clients built from Buckets
+    pub connect: Option<Duration>,
+    /// Time allowed for a single attempt of a request
+    pub request: Option<Duration>,
+    /// Time allowed for a request and all of its retries
+    pub operation: Option<Duration>,
+}
+
+impl TimeoutConfig {
+    /// Run call with these request and operation timeouts in place of
+    /// those of the clients it uses. The connect timeout is set on the
+    /// HTTP client when it is built so it is not replaced
+    pub async fn scope<F: Future>(self, call: F) -> F::Output {
+        SCOPED.scope(self, call).await
+    }
+
+    /// Timeouts of the enclosing scope, self outside of one
+    fn current(&self) -> TimeoutConfig {
+        SCOPED.try_with(|scoped| *scoped).unwrap_or(*self)
+    }
+
+    /// Send request through policy, every attempt bounded by the
+    /// request timeout and all of them by the operation timeout
+    pub async fn send<T, F, Fut>(
+        &self,
+        policy: &RetryPolicy,
+        mut request: F,
+    ) -> Result<T, ErrorDetails>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ErrorDetails>>,
+    {
+        let timeouts = self.current();
+        let limit = timeouts.request;
+        let attempts = policy.retry(|| deadline(limit, request()));
+        deadline(timeouts.operation, attempts).await
+    }
+
+    /// Send a request that is not safe to repeat a single time
+    pub async fn send_once<T, Fut>(
+        &self,
+        request: Fut,
+    ) -> Result<T, ErrorDetails>
+    where
+        Fut: Future<Output = Result<T, ErrorDetails>>,
+    {
+        let timeouts = self.current();
+        let limit = match (timeouts.request, timeouts.operation) {
+            (Some(request), Some(operation)) => Some(request.min(operation)),
+            (request, operation) => request.or(operation),
+        };
+        deadline(limit, request).await
+    }
+}
+
+/// Fail with a timeout when request has not completed within limit
+async fn deadline<T, Fut>(
+    limit: Option<Duration>,
+    request: Fut,
+) -> Result<T, ErrorDetails>
+where
+    Fut: Future<Output = Result<T, ErrorDetails>>,
+{
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, request)
+            .await
+            .unwrap_or_else(|_| {
+                Err(ErrorDetails::new(
+                    ErrorKind::Timeout,
+                    format!("Request timed out after {:?}", limit),
+                ))
+            }),
+        None => request.await,
+    }
+}